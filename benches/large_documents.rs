@@ -0,0 +1,115 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::{json, Map, Value};
+
+use json_diff::options::DiffOptions;
+use json_diff::process::{compare_jsons, match_json, match_json_with_options};
+
+/// serde_json refuses to parse documents nested deeper than 128 levels
+const PARSEABLE_DEPTH: usize = 100;
+
+/// An object with `n` keys, every `step`th value differs between the two variants
+fn wide_object(n: usize, step: usize, variant: usize) -> Value {
+    Value::Object(
+        (0..n)
+            .map(|i| {
+                let value = if i % step == 0 { i + variant } else { i };
+                (
+                    format!("key_{i}"),
+                    json!({"value": value, "name": format!("entry {i}")}),
+                )
+            })
+            .collect::<Map<String, Value>>(),
+    )
+}
+
+/// Objects nested `depth` levels deep with a leaf that differs between the two variants
+fn deep_object(depth: usize, variant: usize) -> Value {
+    (0..depth).fold(
+        json!({"leaf": variant}),
+        |inner, level| json!({"level": level, "child": inner}),
+    )
+}
+
+/// An array of `n` records where every `step`th record changed a field and a record was
+/// inserted in the middle of the second variant
+fn large_array(n: usize, step: usize, variant: usize) -> Value {
+    let mut records: Vec<Value> = (0..n)
+        .map(|i| {
+            let score = if i % step == 0 { i * variant } else { i };
+            json!({"id": i, "score": score, "tags": ["a", "b"]})
+        })
+        .collect();
+    if variant > 0 {
+        records.insert(n / 2, json!({"id": "inserted"}));
+    }
+    Value::Array(records)
+}
+
+fn pre_parsed(c: &mut Criterion) {
+    let cases = [
+        (
+            "wide object, 10k keys",
+            wide_object(10_000, 100, 0),
+            wide_object(10_000, 100, 1),
+        ),
+        (
+            "deep object, 1k levels",
+            deep_object(1000, 0),
+            deep_object(1000, 1),
+        ),
+        (
+            "two arrays of 10k records",
+            large_array(10_000, 250, 0),
+            large_array(10_000, 250, 1),
+        ),
+    ];
+    for (name, left, right) in &cases {
+        c.bench_function(&format!("match_json: {name}"), |b| {
+            b.iter(|| match_json(black_box(left), black_box(right)))
+        });
+    }
+}
+
+fn end_to_end(c: &mut Criterion) {
+    let cases = [
+        (
+            "wide object, 10k keys",
+            wide_object(10_000, 100, 0),
+            wide_object(10_000, 100, 1),
+        ),
+        (
+            "deep object, 100 levels",
+            deep_object(PARSEABLE_DEPTH, 0),
+            deep_object(PARSEABLE_DEPTH, 1),
+        ),
+        (
+            "two arrays of 10k records",
+            large_array(10_000, 250, 0),
+            large_array(10_000, 250, 1),
+        ),
+    ];
+    let options = DiffOptions::default();
+    for (name, left, right) in &cases {
+        let (left, right) = (left.to_string(), right.to_string());
+        c.bench_function(&format!("compare_jsons: {name}"), |b| {
+            b.iter(|| compare_jsons(black_box(&left), black_box(&right), &options, None).unwrap())
+        });
+    }
+}
+
+/// Arrays above `--array-threshold` are compared as multisets, which has to stay fast for
+/// arrays repeating the same value
+fn multiset(c: &mut Criterion) {
+    let left = Value::Array(vec![Value::Null; 100_000]);
+    let mut right = vec![Value::Null; 100_000];
+    right[50_000] = json!(0);
+    let right = Value::Array(right);
+    let options = DiffOptions::default().array_threshold(Some(10));
+    c.bench_function(
+        "match_json_with_options: 100k equal values as multiset",
+        |b| b.iter(|| match_json_with_options(black_box(&left), black_box(&right), &options)),
+    );
+}
+
+criterion_group!(benches, pre_parsed, end_to_end, multiset);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::{json, Value};
+
+use json_diff::options::DiffOptions;
+use json_diff::process::{match_json, match_json_with_options};
+
+/// An array of `n` objects with a few nested levels each, so cloning an element is not free
+fn records(n: usize, offset: usize) -> Value {
+    Value::Array(
+        (0..n)
+            .map(|i| {
+                json!({
+                    "id": (i + offset) % n,
+                    "name": format!("record {i}"),
+                    "tags": ["a", "b", "c"],
+                    "nested": {"values": [i, i + 1, i + 2], "flag": i % 2 == 0},
+                })
+            })
+            .collect(),
+    )
+}
+
+fn sorted_arrays(c: &mut Criterion) {
+    let left = records(2000, 0);
+    let right = records(2000, 7);
+    let sorted = DiffOptions::default().sort_arrays(true);
+    c.bench_function("sort 2000 objects", |b| {
+        b.iter(|| match_json_with_options(black_box(&left), black_box(&right), &sorted))
+    });
+}
+
+fn changed_subtrees(c: &mut Criterion) {
+    // every element changes its type, so the whole subtree ends up in the mismatch
+    let left = records(2000, 0);
+    let right = Value::Array(
+        left.as_array()
+            .unwrap()
+            .iter()
+            .map(|v| Value::Array(vec![v.clone()]))
+            .collect(),
+    );
+    c.bench_function("clone 2000 changed subtrees", |b| {
+        b.iter(|| match_json(black_box(&left), black_box(&right)))
+    });
+    c.bench_function("baseline: clone both documents", |b| {
+        b.iter(|| (black_box(&left).clone(), black_box(&right).clone()))
+    });
+}
+
+criterion_group!(benches, sorted_arrays, changed_subtrees);
+criterion_main!(benches);
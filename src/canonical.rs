@@ -0,0 +1,151 @@
+use serde_json::{Map, Number, Value};
+
+/// Largest integer an `f64` represents exactly together with all smaller ones
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Brings a value into a canonical form in the spirit of RFC 8785 (JCS), so that documents
+/// differing only in key order or number notation compare equal:
+/// * object keys are sorted by their UTF-16 code units
+/// * floats without a fractional part inside the exact `f64` integer range become integers,
+///   so `1.0`, `1e0` and `1` are the same number and `-0.0` becomes `0`
+///
+/// Integers outside that range are kept as they are instead of being rounded to an `f64`.
+pub fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize(value)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(canonicalize).collect()),
+        Value::Number(number) => Value::Number(canonical_number(number)),
+        scalar => scalar,
+    }
+}
+
+/// Sorts the keys of all objects by their bytes and leaves everything else as it is
+pub fn sort_keys(value: Value) -> Value {
+    sort_keys_with(value, false)
+}
+
+/// Like `sort_keys`, with `numeric` the keys of an object that all parse as integers are
+/// sorted by their value instead, so `"2"` comes before `"10"`. Objects with any other key
+/// are still sorted by bytes.
+pub fn sort_keys_with(value: Value, numeric: bool) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            let numbers: Option<Vec<i128>> = numeric
+                .then(|| entries.iter().map(|(key, _)| key.parse().ok()).collect())
+                .flatten();
+            match numbers {
+                Some(numbers) => {
+                    let mut keyed: Vec<_> = numbers.into_iter().zip(entries).collect();
+                    // equal numbers such as "1" and "01" keep a stable order by their bytes
+                    keyed.sort_by(|(x, (a, _)), (y, (b, _))| x.cmp(y).then_with(|| a.cmp(b)));
+                    entries = keyed.into_iter().map(|(_, entry)| entry).collect();
+                }
+                None => entries.sort_by(|(a, _), (b, _)| a.cmp(b)),
+            }
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys_with(value, numeric)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| sort_keys_with(value, numeric))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+fn canonical_number(number: Number) -> Number {
+    match number.as_f64() {
+        Some(float)
+            if !number.is_i64()
+                && !number.is_u64()
+                && float.fract() == 0.0
+                && float.abs() < MAX_SAFE_INTEGER =>
+        {
+            Number::from(float as i64)
+        }
+        _ => number,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn numbers_are_canonical() {
+        let value: Value =
+            serde_json::from_str("[1.0, 1e2, -0.0, 1.5, 18446744073709551615]").unwrap();
+        assert_eq!(
+            canonicalize(value),
+            json!([1, 100, 0, 1.5, 18446744073709551615u64])
+        );
+    }
+
+    #[test]
+    fn keys_are_sorted_by_utf16_units() {
+        let value = json!({"b": {"z": 1, "a": 2}, "\u{e000}": 1, "\u{10000}": 2, "a": 3});
+        let canonical = canonicalize(value);
+        let keys: Vec<&String> = canonical.as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["a", "b", "\u{10000}", "\u{e000}"]);
+        let inner: Vec<&String> = canonical["b"].as_object().unwrap().keys().collect();
+        assert_eq!(inner, vec!["a", "z"]);
+    }
+
+    #[test]
+    fn sort_keys_keeps_numbers_and_array_order() {
+        let value: Value =
+            serde_json::from_str(r#"{"b": [{"y": 1.0, "x": 2}, 1], "a": 1e2}"#).unwrap();
+        // with arbitrary precision numbers keep the text they were written with
+        let a = if cfg!(feature = "arbitrary_precision") {
+            "1e+2"
+        } else {
+            "100.0"
+        };
+        assert_eq!(
+            serde_json::to_string(&sort_keys(value)).unwrap(),
+            format!(r#"{{"a":{a},"b":[{{"x":2,"y":1.0}},1]}}"#)
+        );
+    }
+
+    #[test]
+    fn numeric_keys_are_sorted_by_value() {
+        let value = json!({"10": {"b": 1, "a": 2}, "2": 1, "-1": 1, "01": 1, "1": 1});
+        let keys =
+            |value: &Value| -> Vec<String> { value.as_object().unwrap().keys().cloned().collect() };
+        assert_eq!(
+            keys(&sort_keys_with(value.clone(), true)),
+            vec!["-1", "01", "1", "2", "10"]
+        );
+        assert_eq!(
+            keys(&sort_keys(value.clone())),
+            vec!["-1", "01", "1", "10", "2"]
+        );
+        assert_eq!(keys(&sort_keys_with(value, true)["10"]), vec!["a", "b"]);
+        let mixed = json!({"10": 1, "2": 1, "x": 1});
+        assert_eq!(keys(&sort_keys_with(mixed, true)), vec!["10", "2", "x"]);
+    }
+
+    #[test]
+    fn canonical_documents_have_no_diffs() {
+        let a = canonicalize(serde_json::from_str(r#"{"x": 1.0, "y": [2e0]}"#).unwrap());
+        let b = canonicalize(serde_json::from_str(r#"{"y": [2], "x": 1}"#).unwrap());
+        assert!(crate::process::match_json(&a, &b).is_empty());
+    }
+}
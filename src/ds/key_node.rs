@@ -1,12 +1,135 @@
-use crate::enums::ValueType;
+use crate::enums::{JsonType, ValueType};
+use indexmap::IndexMap;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::borrow::Cow;
+
+/// Separator between the segments of a flat path like `a->b->[l: 0]`
+pub const PATH_SEPARATOR: &str = "->";
+
+pub(crate) fn is_array_label(segment: &str) -> bool {
+    array_label_index(segment).is_some()
+}
+
+/// Left index of an array label like `[l: 3]`, `[l: 3]-<value>` or `[l: 3 r: 4]`
+pub(crate) fn array_label_index(segment: &str) -> Option<usize> {
+    array_label_indices(segment).map(|(left, _)| left)
+}
+
+/// Right index of an array label, the same as the left one unless the label names both
+pub(crate) fn array_label_right_index(segment: &str) -> Option<usize> {
+    array_label_indices(segment).map(|(left, right)| right.unwrap_or(left))
+}
+
+/// Left index and, if it differs, right index of an array label
+fn array_label_indices(segment: &str) -> Option<(usize, Option<usize>)> {
+    let parse = |index: &str| {
+        (!index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| index.parse().ok())
+            .flatten()
+    };
+    let (indices, _) = segment.strip_prefix("[l: ")?.split_once(']')?;
+    match indices.split_once(" r: ") {
+        Some((left, right)) => Some((parse(left)?, Some(parse(right)?))),
+        None => Some((parse(indices)?, None)),
+    }
+}
+
+/// Label of a pair of array elements that are compared with each other, it names the right
+/// index only where it differs from the left one: `[l: 2]` or `[l: 2 r: 3]`
+pub(crate) fn array_pair_label(left: usize, right: usize) -> String {
+    if left == right {
+        format!("[l: {left}]")
+    } else {
+        format!("[l: {left} r: {right}]")
+    }
+}
+
+/// Value of a one-sided array entry, which is part of its label: `[l: 3]-<value>`
+pub(crate) fn array_label_value(segment: &str) -> Option<Value> {
+    array_label_index(segment)?;
+    let (_, value) = segment.split_once("]-")?;
+    serde_json::from_str(value).ok()
+}
+
+/// Turns path segments into a JSON pointer (RFC 6901), array labels become their index
+pub fn json_pointer<S: AsRef<str>>(segments: &[S]) -> String {
+    segments
+        .iter()
+        .map(|segment| {
+            let segment = segment.as_ref();
+            match array_label_index(segment) {
+                Some(index) => format!("/{index}"),
+                None => format!("/{}", segment.replace('~', "~0").replace('/', "~1")),
+            }
+        })
+        .collect()
+}
+
+/// Quotes an object key as a json string if it contains `separator` or starts with a quote,
+/// so that flat paths can be split unambiguously. Array labels are never quoted.
+pub fn escape_key<'a>(key: &'a str, separator: &str) -> Cow<'a, str> {
+    if is_array_label(key) || !(key.contains(separator) || key.starts_with('"')) {
+        Cow::Borrowed(key)
+    } else {
+        Cow::Owned(Value::String(key.to_string()).to_string())
+    }
+}
+
+/// Joins path segments with the separator, quoting keys where needed
+pub fn join_path<S: AsRef<str>>(segments: &[S]) -> String {
+    join_path_with(segments, PATH_SEPARATOR)
+}
+
+/// Like `join_path` with another separator
+pub fn join_path_with<S: AsRef<str>>(segments: &[S], separator: &str) -> String {
+    segments
+        .iter()
+        .map(|segment| escape_key(segment.as_ref(), separator))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
 
 #[derive(Debug, PartialEq)]
 pub enum KeyNode {
     Nil,
     Value(Value, Value),
-    Node(HashMap<String, KeyNode>),
+    Node(IndexMap<String, KeyNode>),
+}
+
+/// Splits a flat path joined with `separator` back into its segments. A one-sided array entry
+/// like `[l: 0]-<value>` always ends the path and stays one segment.
+pub(crate) fn split_path(path: &str, separator: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        let quoted = rest.starts_with('"').then(|| {
+            let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<String>();
+            stream
+                .next()
+                .and_then(Result::ok)
+                .map(|key| (key, stream.byte_offset()))
+        });
+        let (segment, end) = match quoted.flatten() {
+            Some(quoted) => quoted,
+            None if is_array_label(rest) => {
+                let close = rest.find(']').unwrap_or(rest.len()) + 1;
+                let after = &rest[close..];
+                if after.starts_with('-') && !after.starts_with(separator) {
+                    (rest.to_string(), rest.len())
+                } else {
+                    (rest[..close].to_string(), close)
+                }
+            }
+            None => {
+                let end = rest.find(separator).unwrap_or(rest.len());
+                (rest[..end].to_string(), end)
+            }
+        };
+        segments.push(segment);
+        rest = &rest[end..];
+        rest = rest.strip_prefix(separator).unwrap_or(rest);
+    }
+    segments
 }
 
 fn truncate(s: &str, max_chars: usize) -> String {
@@ -14,17 +137,160 @@ fn truncate(s: &str, max_chars: usize) -> String {
         None => String::from(s),
         Some((idx, _)) => {
             let shorter = &s[..idx];
-            let snip = "//SNIP//";
+            let snip = "…(truncated)";
             let new_s = format!("{}{}", shorter, snip);
             new_s
         }
     }
 }
 
+fn display_value(value: &Value, max_chars: usize, pretty: bool) -> String {
+    if pretty && (value.is_object() || value.is_array()) {
+        let pretty = serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string());
+        let indented: Vec<String> = truncate(&pretty, max_chars)
+            .lines()
+            .map(|line| format!("    {line}"))
+            .collect();
+        format!("\n{}\n", indented.join("\n"))
+    } else {
+        truncate(value.to_string().as_str(), max_chars)
+    }
+}
+
 impl KeyNode {
+    /// Number of leaves below this node, intermediate nodes are not counted
+    pub fn count_leaves(&self) -> usize {
+        match self {
+            KeyNode::Nil => 0,
+            KeyNode::Value(_, _) => 1,
+            KeyNode::Node(map) => map
+                .values()
+                .map(|child| match child {
+                    KeyNode::Nil => 1,
+                    _ => child.count_leaves(),
+                })
+                .sum(),
+        }
+    }
+
+    /// Paths of all leaves below this node, one segment per key
+    pub fn leaf_paths(&self) -> Vec<Vec<String>> {
+        let mut paths = Vec::new();
+        self.for_each_leaf(&mut |path, _| paths.push(path.to_vec()));
+        paths
+    }
+
+    /// Calls `f` with the path of every leaf and, for value mismatches, the left and right value
+    pub fn for_each_leaf<'a>(
+        &'a self,
+        f: &mut impl FnMut(&[String], Option<(&'a Value, &'a Value)>),
+    ) {
+        self.walk_leaves(&mut Vec::new(), f);
+    }
+
+    fn walk_leaves<'a>(
+        &'a self,
+        prefix: &mut Vec<String>,
+        f: &mut impl FnMut(&[String], Option<(&'a Value, &'a Value)>),
+    ) {
+        match self {
+            KeyNode::Nil if prefix.is_empty() => {}
+            KeyNode::Nil => f(prefix, None),
+            KeyNode::Value(a, b) => f(prefix, Some((a, b))),
+            KeyNode::Node(map) => {
+                for (key, value) in map {
+                    prefix.push(key.clone());
+                    value.walk_leaves(prefix, f);
+                    prefix.pop();
+                }
+            }
+        }
+    }
+
+    /// Like `for_each_leaf`, but hands the values of value mismatches over instead of lending
+    /// them
+    pub fn into_each_leaf(self, f: &mut impl FnMut(&[String], Option<(Value, Value)>)) {
+        self.into_walk_leaves(&mut Vec::new(), f);
+    }
+
+    fn into_walk_leaves(
+        self,
+        prefix: &mut Vec<String>,
+        f: &mut impl FnMut(&[String], Option<(Value, Value)>),
+    ) {
+        match self {
+            KeyNode::Nil if prefix.is_empty() => {}
+            KeyNode::Nil => f(prefix, None),
+            KeyNode::Value(a, b) => f(prefix, Some((a, b))),
+            KeyNode::Node(map) => {
+                for (key, value) in map {
+                    prefix.push(key);
+                    value.into_walk_leaves(prefix, f);
+                    prefix.pop();
+                }
+            }
+        }
+    }
+
+    /// Drops every leaf whose path does not satisfy `keep`, nodes left without children
+    /// collapse to `Nil`
+    pub(crate) fn filter_leaves(
+        self,
+        prefix: &mut Vec<String>,
+        keep: &impl Fn(&[String]) -> bool,
+    ) -> KeyNode {
+        match self {
+            KeyNode::Nil => KeyNode::Nil,
+            KeyNode::Value(..) if !keep(prefix) => KeyNode::Nil,
+            KeyNode::Value(..) => self,
+            KeyNode::Node(map) => {
+                let mut kept = IndexMap::new();
+                for (key, child) in map {
+                    prefix.push(key);
+                    let child = match child {
+                        KeyNode::Nil if keep(prefix) => Some(KeyNode::Nil),
+                        KeyNode::Nil => None,
+                        child => Some(child.filter_leaves(prefix, keep))
+                            .filter(|child| *child != KeyNode::Nil),
+                    };
+                    let key = prefix.pop().unwrap_or_default();
+                    if let Some(child) = child {
+                        kept.insert(key, child);
+                    }
+                }
+                if kept.is_empty() {
+                    KeyNode::Nil
+                } else {
+                    KeyNode::Node(kept)
+                }
+            }
+        }
+    }
+
     pub fn absolute_keys_to_vec(&self, max_display_length: Option<usize>) -> Vec<ValueType> {
+        self.absolute_keys_to_vec_with(max_display_length, false, PATH_SEPARATOR, false)
+    }
+
+    /// Like `absolute_keys_to_vec`, with `pretty_values` objects and arrays are pretty printed
+    /// on their own indented lines, scalars stay on one line. Path segments are joined with
+    /// `separator`. With `show_types` both values are followed by their json type, e.g.
+    /// `5 (number)`.
+    pub fn absolute_keys_to_vec_with(
+        &self,
+        max_display_length: Option<usize>,
+        pretty_values: bool,
+        separator: &str,
+        show_types: bool,
+    ) -> Vec<ValueType> {
         let mut vec = Vec::new();
-        self.absolute_keys(&mut vec, None, max_display_length);
+        self.absolute_keys(
+            &mut vec,
+            None,
+            max_display_length,
+            pretty_values,
+            separator,
+            show_types,
+        );
         vec
     }
 
@@ -33,11 +299,22 @@ impl KeyNode {
         keys: &mut Vec<ValueType>,
         key_from_root: Option<String>,
         max_display_length: Option<usize>,
+        pretty_values: bool,
+        separator: &str,
+        show_types: bool,
     ) {
         let max_display_length = max_display_length.unwrap_or(4000);
+        let display = |value: &Value| {
+            let text = display_value(value, max_display_length, pretty_values);
+            if show_types {
+                format!("{text} ({})", JsonType::from(value))
+            } else {
+                text
+            }
+        };
         let val_key = |key: Option<String>| {
             key.map(|mut s| {
-                s.push_str("->");
+                s.push_str(separator);
                 s
             })
             .unwrap_or_default()
@@ -50,15 +327,23 @@ impl KeyNode {
             }
             KeyNode::Value(a, b) => keys.push(ValueType::new_value(
                 val_key(key_from_root),
-                truncate(a.to_string().as_str(), max_display_length),
-                truncate(b.to_string().as_str(), max_display_length),
+                display(a),
+                display(b),
+                JsonType::from(a) != JsonType::from(b),
             )),
             KeyNode::Node(map) => {
                 for (key, value) in map {
                     value.absolute_keys(
                         keys,
-                        Some(format!("{}{}", val_key(key_from_root.clone()), key)),
+                        Some(format!(
+                            "{}{}",
+                            val_key(key_from_root.clone()),
+                            escape_key(key, separator)
+                        )),
                         Some(max_display_length),
+                        pretty_values,
+                        separator,
+                        show_types,
                     )
                 }
             }
@@ -1,11 +1,99 @@
-use crate::ds::key_node::KeyNode;
-use crate::enums::{DiffType, ValueType};
+use crate::ds::key_node::{array_label_value, join_path, join_path_with, KeyNode, PATH_SEPARATOR};
+use crate::enums::{ChangeKind, DiffType, JsonType, ValueType};
+use crate::path_glob::{self, PathGlob};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
 
 #[derive(Debug, PartialEq)]
 pub struct Mismatch {
     pub left_only_keys: KeyNode,
     pub right_only_keys: KeyNode,
     pub keys_in_both: KeyNode,
+    /// Array elements found at another index on the right side, only detected with
+    /// `DiffOptions::detect_moves`
+    pub moved: Vec<Move>,
+    /// Number of leaves equal on both sides, only counted with `DiffOptions::count_equal`
+    pub equal_leaves: usize,
+}
+
+/// An array element that is equal on both sides but moved from index `from` to `to`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Move {
+    /// Path of the array
+    pub path: Vec<String>,
+    pub from: usize,
+    pub to: usize,
+    pub value: Value,
+}
+
+impl Move {
+    /// Path of the element at its left index
+    pub fn element_path(&self) -> Vec<String> {
+        let mut path = self.path.clone();
+        path.push(format!("[l: {}]", self.from));
+        path
+    }
+}
+
+/// One difference as `Mismatch::for_each_diff` finds it
+pub(crate) struct Leaf<'a, 'p> {
+    pub path: &'p [String],
+    pub kind: ChangeKind,
+    /// The left value, if known: of changed values, moved elements and removed array elements
+    pub left: Option<Cow<'a, Value>>,
+    /// The right value, if known: of changed values, moved elements and added array elements
+    pub right: Option<Cow<'a, Value>>,
+    /// The right index of a moved element
+    pub moved_to: Option<usize>,
+}
+
+/// Number of leaf differences per change kind
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DiffCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub moved: usize,
+}
+
+impl DiffCounts {
+    /// The count of one kind of difference
+    pub fn of(&self, kind: ChangeKind) -> usize {
+        match kind {
+            ChangeKind::Added => self.added,
+            ChangeKind::Removed => self.removed,
+            ChangeKind::Changed => self.changed,
+            ChangeKind::Moved => self.moved,
+        }
+    }
+}
+
+impl Display for DiffCounts {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} added, {} removed, {} changed",
+            self.added, self.removed, self.changed
+        )?;
+        if self.moved > 0 {
+            write!(f, ", {} moved", self.moved)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single difference with its path split into key segments.
+/// `left` and `right` are set for changed and moved values and for the side of added or removed
+/// array elements, the mismatch tree does not keep the values of added or removed keys. A moved
+/// element has the path of its left index and its right index in `moved_to`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    pub path: Vec<String>,
+    pub kind: ChangeKind,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+    pub moved_to: Option<usize>,
 }
 
 impl Mismatch {
@@ -14,6 +102,8 @@ impl Mismatch {
             left_only_keys: l,
             right_only_keys: r,
             keys_in_both: u,
+            moved: Vec::new(),
+            equal_leaves: 0,
         }
     }
 
@@ -22,13 +112,245 @@ impl Mismatch {
             left_only_keys: KeyNode::Nil,
             keys_in_both: KeyNode::Nil,
             right_only_keys: KeyNode::Nil,
+            moved: Vec::new(),
+            equal_leaves: 0,
         }
     }
 
+    /// Sets the moved array elements
+    pub fn with_moves(mut self, moved: Vec<Move>) -> Mismatch {
+        self.moved = moved;
+        self
+    }
+
+    /// Sets the number of equal leaves
+    pub fn with_equal_leaves(mut self, equal_leaves: usize) -> Mismatch {
+        self.equal_leaves = equal_leaves;
+        self
+    }
+
+    /// True if the compared values are equal, i.e. all three buckets are `KeyNode::Nil` and
+    /// no element moved
     pub fn is_empty(&self) -> bool {
         self.left_only_keys == KeyNode::Nil
             && self.keys_in_both == KeyNode::Nil
             && self.right_only_keys == KeyNode::Nil
+            && self.moved.is_empty()
+    }
+
+    /// The types of both documents if they already differ at the root, e.g. an object compared
+    /// against an array. Nothing below the root is compared in that case.
+    pub fn root_types(&self) -> Option<(JsonType, JsonType)> {
+        match &self.keys_in_both {
+            KeyNode::Value(a, b) if JsonType::from(a) != JsonType::from(b) => {
+                Some((JsonType::from(a), JsonType::from(b)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Drops added and removed keys and keeps only the values that changed at keys in both
+    pub fn values_only(self) -> Mismatch {
+        self.only(&[ChangeKind::Changed])
+    }
+
+    /// Keeps only the differences whose path satisfies `predicate`, in all three buckets.
+    /// The predicate gets the path segments of each leaf, empty for a change of the root value.
+    pub fn retain<F: Fn(&[String]) -> bool>(self, predicate: F) -> Mismatch {
+        let equal_leaves = self.equal_leaves;
+        let mut moved = self.moved;
+        moved.retain(|m| predicate(&m.element_path()));
+        Mismatch::new(
+            self.left_only_keys
+                .filter_leaves(&mut Vec::new(), &predicate),
+            self.right_only_keys
+                .filter_leaves(&mut Vec::new(), &predicate),
+            self.keys_in_both.filter_leaves(&mut Vec::new(), &predicate),
+        )
+        .with_moves(moved)
+        .with_equal_leaves(equal_leaves)
+    }
+
+    /// Drops all differences at or below a path matched by one of `globs`
+    pub fn ignore_paths(self, globs: &[PathGlob]) -> Mismatch {
+        self.retain(|path| !path_glob::ignores(globs, path))
+    }
+
+    /// Keeps only the differences at or below a path matched by one of `globs`, the dual of
+    /// `ignore_paths`. A difference above a matched path, such as a removed object that may
+    /// hold it, is kept as well.
+    pub fn only_paths(self, globs: &[PathGlob]) -> Mismatch {
+        self.retain(|path| path_glob::selects(globs, path))
+    }
+
+    /// Keeps only the buckets of the given change kinds
+    pub fn only(self, kinds: &[ChangeKind]) -> Mismatch {
+        let keep = |node: KeyNode, kind| {
+            if kinds.contains(&kind) {
+                node
+            } else {
+                KeyNode::Nil
+            }
+        };
+        let equal_leaves = self.equal_leaves;
+        let moved = if kinds.contains(&ChangeKind::Moved) {
+            self.moved
+        } else {
+            Vec::new()
+        };
+        Mismatch::new(
+            keep(self.left_only_keys, ChangeKind::Removed),
+            keep(self.right_only_keys, ChangeKind::Added),
+            keep(self.keys_in_both, ChangeKind::Changed),
+        )
+        .with_moves(moved)
+        .with_equal_leaves(equal_leaves)
+    }
+
+    pub fn counts(&self) -> DiffCounts {
+        DiffCounts {
+            added: self.right_only_keys.count_leaves(),
+            removed: self.left_only_keys.count_leaves(),
+            changed: self.keys_in_both.count_leaves(),
+            moved: self.moved.len(),
+        }
+    }
+
+    /// How similar the compared values are in terms of this mismatch, from `0.0` (nothing in
+    /// common) to `1.0` (no differences): the equal leaves of both sides divided by themselves
+    /// plus the differences of both sides. A changed value or moved element differs on both
+    /// sides, an added or removed key or element on one. Unlike `process::similarity` this
+    /// only sees the differences left in the mismatch and needs `DiffOptions::count_equal`.
+    pub fn similarity(&self) -> f64 {
+        let counts = self.counts();
+        let equal = 2 * self.equal_leaves;
+        let total = equal + 2 * (counts.changed + counts.moved) + counts.added + counts.removed;
+        if total == 0 {
+            return 1.0;
+        }
+        equal as f64 / total as f64
+    }
+
+    /// The trees of the mismatch with the kind of change of their leaves, in the order in which
+    /// differences are listed; the moved elements follow them
+    fn buckets<T>(changed: T, removed: T, added: T) -> [(T, ChangeKind); 3] {
+        [
+            (changed, ChangeKind::Changed),
+            (removed, ChangeKind::Removed),
+            (added, ChangeKind::Added),
+        ]
+    }
+
+    /// The values of a leaf: those of a changed value, or for a one-sided array element the
+    /// value on its side, which is part of its label
+    fn leaf_values<V>(
+        path: &[String],
+        kind: ChangeKind,
+        values: Option<(V, V)>,
+        owned: fn(Value) -> V,
+    ) -> (Option<V>, Option<V>) {
+        if let Some((left, right)) = values {
+            return (Some(left), Some(right));
+        }
+        let one_sided = path
+            .last()
+            .and_then(|last| array_label_value(last))
+            .map(owned);
+        match kind {
+            ChangeKind::Removed => (one_sided, None),
+            _ => (None, one_sided),
+        }
+    }
+
+    /// Calls `f` with every difference: changed values first, then removed and added keys and
+    /// moved elements, which are passed at their left index with their value on both sides
+    pub(crate) fn for_each_diff<'a>(&'a self, f: &mut impl FnMut(Leaf<'a, '_>)) {
+        let buckets = Self::buckets(
+            &self.keys_in_both,
+            &self.left_only_keys,
+            &self.right_only_keys,
+        );
+        for (node, kind) in buckets {
+            node.for_each_leaf(&mut |path, values| {
+                let values =
+                    values.map(|(left, right)| (Cow::Borrowed(left), Cow::Borrowed(right)));
+                let (left, right) = Self::leaf_values(path, kind, values, Cow::Owned);
+                f(Leaf {
+                    path,
+                    kind,
+                    left,
+                    right,
+                    moved_to: None,
+                })
+            });
+        }
+        for moved in &self.moved {
+            f(Leaf {
+                path: &moved.element_path(),
+                kind: ChangeKind::Moved,
+                left: Some(Cow::Borrowed(&moved.value)),
+                right: Some(Cow::Borrowed(&moved.value)),
+                moved_to: Some(moved.to),
+            });
+        }
+    }
+
+    /// All differences as structured entries: changed values first, then removed and added
+    /// keys and moved elements
+    pub fn diffs(&self) -> Vec<DiffEntry> {
+        let mut entries = Vec::new();
+        self.for_each_diff(&mut |leaf| {
+            entries.push(DiffEntry {
+                path: leaf.path.to_vec(),
+                kind: leaf.kind,
+                left: leaf.left.map(Cow::into_owned),
+                right: leaf.right.map(Cow::into_owned),
+                moved_to: leaf.moved_to,
+            })
+        });
+        entries
+    }
+
+    /// Like `diffs`, but moves the values out of the mismatch instead of cloning them
+    pub fn into_entries(self) -> Vec<DiffEntry> {
+        let mut entries = Vec::new();
+        let buckets = Self::buckets(self.keys_in_both, self.left_only_keys, self.right_only_keys);
+        for (node, kind) in buckets {
+            node.into_each_leaf(&mut |path, values| {
+                let (left, right) = Self::leaf_values(path, kind, values, |value| value);
+                entries.push(DiffEntry {
+                    path: path.to_vec(),
+                    kind,
+                    left,
+                    right,
+                    moved_to: None,
+                })
+            });
+        }
+        entries.extend(self.moved.into_iter().map(|m| DiffEntry {
+            path: m.element_path(),
+            kind: ChangeKind::Moved,
+            left: Some(m.value.clone()),
+            right: Some(m.value),
+            moved_to: Some(m.to),
+        }));
+        entries
+    }
+
+    /// The path of every difference joined with `->`, without colors or values, in the order
+    /// of `diffs`. Moved elements are listed at their left index.
+    pub fn all_paths(&self) -> Vec<String> {
+        self.all_paths_with_kinds()
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect()
+    }
+
+    /// Like `all_paths`, with the kind of change of each path
+    pub fn all_paths_with_kinds(&self) -> Vec<(ChangeKind, String)> {
+        let mut paths = Vec::new();
+        self.for_each_diff(&mut |leaf| paths.push((leaf.kind, join_path(leaf.path))));
+        paths
     }
 
     pub fn all_diffs(&self) -> Vec<(DiffType, ValueType)> {
@@ -36,23 +358,54 @@ impl Mismatch {
     }
 
     pub fn all_diffs_trunc(&self, truncation_length: Option<usize>) -> Vec<(DiffType, ValueType)> {
+        self.all_diffs_with(truncation_length, false, PATH_SEPARATOR, false)
+    }
+
+    /// All differences, with `pretty_values` changed objects and arrays are pretty printed.
+    /// Path segments are joined with `separator`, with `show_types` changed values are followed
+    /// by their json type.
+    pub fn all_diffs_with(
+        &self,
+        truncation_length: Option<usize>,
+        pretty_values: bool,
+        separator: &str,
+        show_types: bool,
+    ) -> Vec<(DiffType, ValueType)> {
         let both = self
             .keys_in_both
-            .absolute_keys_to_vec(truncation_length)
+            .absolute_keys_to_vec_with(truncation_length, pretty_values, separator, show_types)
             .into_iter()
-            .map(|k| (DiffType::Mismatch, k));
+            .map(|k| {
+                if let KeyNode::Value(a, b) = &self.keys_in_both {
+                    if k.is_type_change() {
+                        let (left, right) = (JsonType::from(a), JsonType::from(b));
+                        return (DiffType::RootMismatch { left, right }, k);
+                    }
+                }
+                if k.is_type_change() {
+                    (DiffType::TypeMismatch, k)
+                } else {
+                    (DiffType::Mismatch, k)
+                }
+            });
         let left = self
             .left_only_keys
-            .absolute_keys_to_vec(truncation_length)
+            .absolute_keys_to_vec_with(truncation_length, false, separator, false)
             .into_iter()
             .map(|k| (DiffType::LeftExtra, k));
         let right = self
             .right_only_keys
-            .absolute_keys_to_vec(truncation_length)
+            .absolute_keys_to_vec_with(truncation_length, false, separator, false)
             .into_iter()
             .map(|k| (DiffType::RightExtra, k));
+        let moved = self.moved.iter().map(|m| {
+            let mut path: Vec<String> = m.path.clone();
+            path.push(format!("[l: {}]-{}", m.from, m.value));
+            let key = format!("{} to [l: {}]", join_path_with(&path, separator), m.to);
+            (DiffType::Moved, ValueType::new_key(key))
+        });
 
-        both.chain(left).chain(right).collect()
+        both.chain(left).chain(right).chain(moved).collect()
     }
 }
 
@@ -65,4 +418,334 @@ mod test {
         let all_diffs = empty.all_diffs();
         assert!(all_diffs.is_empty());
     }
+
+    #[test]
+    fn ignore_paths_prunes_matching_leaves() {
+        let diff = crate::process::match_json(
+            &serde_json::json!({"items": [{"id": 1, "at": 1}, {"id": 2, "at": 2}], "a": {"b": 1}, "c": 1}),
+            &serde_json::json!({"items": [{"id": 1, "at": 3}, {"id": 3, "at": 4}], "a": {}, "d": 1}),
+        );
+        let globs: Vec<PathGlob> = ["items.*.at", "a", "d"]
+            .iter()
+            .map(|g| g.parse().unwrap())
+            .collect();
+        let diff = diff.ignore_paths(&globs);
+        assert_eq!(
+            diff.keys_in_both.leaf_paths(),
+            vec![vec!["items", "[l: 1]", "id"]]
+        );
+        assert_eq!(diff.left_only_keys.leaf_paths(), vec![vec!["c"]]);
+        assert_eq!(diff.right_only_keys, KeyNode::Nil);
+
+        let all: Vec<PathGlob> = vec!["**".parse().unwrap()];
+        let diff = crate::process::match_json(&serde_json::json!(1), &serde_json::json!(2));
+        assert!(diff.ignore_paths(&all).is_empty());
+    }
+
+    #[test]
+    fn only_paths_keeps_nested_allowlist() {
+        let diff = crate::process::match_json(
+            &serde_json::json!({"a": {"b": {"c": 1, "d": 1}, "e": 1}, "f": 1, "g": [{"h": 1, "i": 1}]}),
+            &serde_json::json!({"a": {"b": {"c": 2, "d": 2}, "e": 2}, "f": 2, "g": [{"h": 2, "i": 2}], "j": {}}),
+        );
+        let globs: Vec<PathGlob> = ["a.b.c", "g.*.h", "j.k"]
+            .iter()
+            .map(|g| g.parse().unwrap())
+            .collect();
+        let diff = diff.only_paths(&globs);
+        assert_eq!(
+            diff.keys_in_both.leaf_paths(),
+            vec![vec!["a", "b", "c"], vec!["g", "[l: 0]", "h"]]
+        );
+        // the added object may hold an allowed path
+        assert_eq!(diff.right_only_keys.leaf_paths(), vec![vec!["j"]]);
+
+        let allowed: Vec<PathGlob> = vec!["a".parse().unwrap()];
+        let ignored: Vec<PathGlob> = vec!["a.b".parse().unwrap()];
+        let diff = crate::process::match_json(
+            &serde_json::json!({"a": {"b": 1, "c": 1}, "d": 1}),
+            &serde_json::json!({"a": {"b": 2, "c": 2}, "d": 2}),
+        );
+        let diff = diff.only_paths(&allowed).ignore_paths(&ignored);
+        assert_eq!(diff.keys_in_both.leaf_paths(), vec![vec!["a", "c"]]);
+    }
+
+    #[test]
+    fn retain_keeps_matching_leaves() {
+        let diff = crate::process::match_json(
+            &serde_json::json!({"a": {"x": 1, "y": 1}, "b": [1, 2], "c": 1}),
+            &serde_json::json!({"a": {"x": 2, "y": 2}, "b": [1], "d": 1}),
+        );
+        let diff = diff.retain(|path| path.last().is_some_and(|key| key != "y" && key != "c"));
+        assert_eq!(diff.keys_in_both.leaf_paths(), vec![vec!["a", "x"]]);
+        assert_eq!(
+            diff.left_only_keys.leaf_paths(),
+            vec![vec!["b", "[l: 1]-2"]]
+        );
+        assert_eq!(diff.right_only_keys.leaf_paths(), vec![vec!["d"]]);
+
+        let diff = diff.retain(|path| path[0] == "b");
+        assert_eq!(diff.keys_in_both, KeyNode::Nil);
+        assert_eq!(diff.right_only_keys, KeyNode::Nil);
+        assert!(!diff.is_empty());
+
+        let root = crate::process::match_json(&serde_json::json!(1), &serde_json::json!(2));
+        assert!(!root.retain(|path| path.is_empty()).is_empty());
+    }
+
+    #[test]
+    fn is_empty_checks_all_buckets() {
+        assert!(Mismatch::empty().is_empty());
+        assert!(Mismatch::new(KeyNode::Nil, KeyNode::Nil, KeyNode::Nil).is_empty());
+        let key = || KeyNode::Node(indexmap::indexmap! {"a".to_string() => KeyNode::Nil});
+        assert!(!Mismatch::new(key(), KeyNode::Nil, KeyNode::Nil).is_empty());
+        assert!(!Mismatch::new(KeyNode::Nil, key(), KeyNode::Nil).is_empty());
+        let value = KeyNode::Value(serde_json::json!(1), serde_json::json!(2));
+        assert!(!Mismatch::new(KeyNode::Nil, KeyNode::Nil, value).is_empty());
+    }
+
+    #[test]
+    fn counts_only_leaves() {
+        let diff = Mismatch::new(
+            KeyNode::Node(indexmap::indexmap! {
+                "a".to_string() => KeyNode::Node(indexmap::indexmap! {
+                    "b".to_string() => KeyNode::Nil,
+                    "c".to_string() => KeyNode::Nil,
+                }),
+            }),
+            KeyNode::Node(indexmap::indexmap! {
+                "d".to_string() => KeyNode::Nil,
+            }),
+            KeyNode::Node(indexmap::indexmap! {
+                "e".to_string() => KeyNode::Node(indexmap::indexmap! {
+                    "f".to_string() => KeyNode::Value(serde_json::json!(1), serde_json::json!(2)),
+                }),
+            }),
+        );
+        let counts = diff.counts();
+        assert_eq!(
+            counts,
+            DiffCounts {
+                added: 1,
+                removed: 2,
+                changed: 1,
+                moved: 0,
+            }
+        );
+        assert_eq!(counts.to_string(), "1 added, 2 removed, 1 changed");
+        assert_eq!(Mismatch::empty().counts(), DiffCounts::default());
+    }
+
+    #[test]
+    fn values_only_keeps_changed_values() {
+        let changed = KeyNode::Node(indexmap::indexmap! {
+            "c".to_string() => KeyNode::Value(serde_json::json!(1), serde_json::json!(2)),
+        });
+        let diff = Mismatch::new(
+            KeyNode::Node(indexmap::indexmap! { "a".to_string() => KeyNode::Nil }),
+            KeyNode::Node(indexmap::indexmap! { "b".to_string() => KeyNode::Nil }),
+            changed,
+        )
+        .values_only();
+        assert_eq!(diff.left_only_keys, KeyNode::Nil);
+        assert_eq!(diff.right_only_keys, KeyNode::Nil);
+        assert_eq!(diff.counts().changed, 1);
+    }
+
+    #[test]
+    fn counts_per_kind() {
+        let counts = DiffCounts {
+            added: 1,
+            removed: 2,
+            changed: 3,
+            moved: 4,
+        };
+        assert_eq!(counts.of(ChangeKind::Added), 1);
+        assert_eq!(counts.of(ChangeKind::Removed), 2);
+        assert_eq!(counts.of(ChangeKind::Changed), 3);
+        assert_eq!(counts.of(ChangeKind::Moved), 4);
+    }
+
+    #[test]
+    fn paths_of_all_kinds() {
+        let diff = Mismatch::new(
+            KeyNode::Node(indexmap::indexmap! { "a".to_string() => KeyNode::Nil }),
+            KeyNode::Node(indexmap::indexmap! { "[l: 1]-2".to_string() => KeyNode::Nil }),
+            KeyNode::Node(indexmap::indexmap! {
+                "c".to_string() => KeyNode::Node(indexmap::indexmap! {
+                    "d".to_string() => KeyNode::Value(serde_json::json!(1), serde_json::json!(2)),
+                }),
+            }),
+        )
+        .with_moves(vec![Move {
+            path: vec!["e".to_string()],
+            from: 0,
+            to: 2,
+            value: serde_json::json!("x"),
+        }]);
+        assert_eq!(diff.all_paths(), vec!["c->d", "a", "[l: 1]-2", "e->[l: 0]"]);
+        assert_eq!(
+            diff.all_paths_with_kinds()
+                .into_iter()
+                .map(|(kind, _)| kind)
+                .collect::<Vec<_>>(),
+            vec![
+                ChangeKind::Changed,
+                ChangeKind::Removed,
+                ChangeKind::Added,
+                ChangeKind::Moved
+            ]
+        );
+        assert!(Mismatch::empty().all_paths().is_empty());
+    }
+
+    #[test]
+    fn only_selected_kinds() {
+        let diff = || {
+            Mismatch::new(
+                KeyNode::Node(indexmap::indexmap! { "a".to_string() => KeyNode::Nil }),
+                KeyNode::Node(indexmap::indexmap! { "b".to_string() => KeyNode::Nil }),
+                KeyNode::Node(indexmap::indexmap! {
+                    "c".to_string() => KeyNode::Value(serde_json::json!(1), serde_json::json!(2)),
+                }),
+            )
+        };
+        let counts = diff()
+            .only(&[ChangeKind::Added, ChangeKind::Removed])
+            .counts();
+        assert_eq!(
+            counts,
+            DiffCounts {
+                added: 1,
+                removed: 1,
+                changed: 0,
+                moved: 0,
+            }
+        );
+        assert!(diff().only(&[]).is_empty());
+        assert_eq!("removed".parse(), Ok(ChangeKind::Removed));
+        assert_eq!("moved".parse(), Ok(ChangeKind::Moved));
+        assert!("renamed".parse::<ChangeKind>().is_err());
+    }
+
+    #[test]
+    fn structured_diffs() {
+        let diff = Mismatch::new(
+            KeyNode::Node(indexmap::indexmap! {
+                "a".to_string() => KeyNode::Nil,
+            }),
+            KeyNode::Node(indexmap::indexmap! {
+                "b".to_string() => KeyNode::Node(indexmap::indexmap! {
+                    "c".to_string() => KeyNode::Nil,
+                }),
+            }),
+            KeyNode::Node(indexmap::indexmap! {
+                "d".to_string() => KeyNode::Value(serde_json::json!(1), serde_json::json!(2)),
+            }),
+        );
+        let entries = diff.diffs();
+        assert_eq!(
+            entries,
+            vec![
+                DiffEntry {
+                    path: vec!["d".to_string()],
+                    kind: ChangeKind::Changed,
+                    left: Some(serde_json::json!(1)),
+                    right: Some(serde_json::json!(2)),
+                    moved_to: None,
+                },
+                DiffEntry {
+                    path: vec!["a".to_string()],
+                    kind: ChangeKind::Removed,
+                    left: None,
+                    right: None,
+                    moved_to: None,
+                },
+                DiffEntry {
+                    path: vec!["b".to_string(), "c".to_string()],
+                    kind: ChangeKind::Added,
+                    left: None,
+                    right: None,
+                    moved_to: None,
+                },
+            ]
+        );
+        assert!(Mismatch::empty().diffs().is_empty());
+    }
+
+    #[test]
+    fn entries_of_each_kind() {
+        let diff = crate::process::match_json_with_options(
+            &serde_json::json!({"a": 1, "b": true, "list": ["x", "y", "z"], "more": [1]}),
+            &serde_json::json!({"a": 2, "c": null, "list": ["y", "z", "x"], "more": [1, {"d": 3}]}),
+            &crate::options::DiffOptions::default().detect_moves(true),
+        );
+        let borrowed = diff.diffs();
+        let entries = diff.into_entries();
+        assert_eq!(entries, borrowed);
+        let kinds: Vec<(String, ChangeKind)> = entries
+            .iter()
+            .map(|entry| (entry.path.join("->"), entry.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("a".to_string(), ChangeKind::Changed),
+                ("b".to_string(), ChangeKind::Removed),
+                ("c".to_string(), ChangeKind::Added),
+                (r#"more->[l: 1]-{"d":3}"#.to_string(), ChangeKind::Added),
+                ("list->[l: 0]".to_string(), ChangeKind::Moved),
+            ]
+        );
+        assert_eq!(entries[0].left, Some(serde_json::json!(1)));
+        assert_eq!(entries[0].right, Some(serde_json::json!(2)));
+        assert_eq!(entries[3].left, None);
+        assert_eq!(entries[3].right, Some(serde_json::json!({"d": 3})));
+        assert_eq!(entries[4].moved_to, Some(2));
+        assert!(Mismatch::empty().into_entries().is_empty());
+    }
+
+    #[test]
+    fn type_changes_are_classified() {
+        let diff = Mismatch::new(
+            KeyNode::Nil,
+            KeyNode::Nil,
+            KeyNode::Node(indexmap::indexmap! {
+                "a".to_string() => KeyNode::Value(serde_json::json!(5), serde_json::json!("5")),
+                "b".to_string() => KeyNode::Value(serde_json::json!(5), serde_json::json!(6)),
+            }),
+        );
+        let all_diffs = diff.all_diffs();
+        assert_eq!(all_diffs.len(), 2);
+        for (d_type, value) in all_diffs {
+            match value.get_key() {
+                "a->" => assert!(matches!(d_type, DiffType::TypeMismatch)),
+                "b->" => assert!(matches!(d_type, DiffType::Mismatch)),
+                key => panic!("unexpected key {key}"),
+            }
+        }
+    }
+
+    #[test]
+    fn root_type_change_names_both_types() {
+        let diff = Mismatch::new(
+            KeyNode::Nil,
+            KeyNode::Nil,
+            KeyNode::Value(serde_json::json!({"a": 1}), serde_json::json!([1])),
+        );
+        let lines: Vec<String> = diff
+            .all_diffs()
+            .into_iter()
+            .map(|(d_type, value)| format!("{d_type}: {value}"))
+            .collect();
+        assert_eq!(lines, vec![r#"root: object != array: {{"a":1}!=[1]}"#]);
+
+        let diff = Mismatch::new(
+            KeyNode::Nil,
+            KeyNode::Nil,
+            KeyNode::Value(serde_json::json!(1), serde_json::json!(2)),
+        );
+        assert!(matches!(diff.all_diffs()[0].0, DiffType::Mismatch));
+        assert_eq!(diff.root_types(), None);
+    }
 }
@@ -1,2 +1,3 @@
 pub mod key_node;
 pub mod mismatch;
+pub mod three_way;
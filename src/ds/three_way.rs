@@ -0,0 +1,67 @@
+use std::fmt::{Display, Formatter};
+
+use crate::ds::key_node::join_path;
+
+/// Which side of a three-way comparison changed a path relative to the base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeOrigin {
+    /// Only "mine" changed this path
+    Ours,
+    /// Only "theirs" changed this path
+    Theirs,
+    /// Both sides made the identical change
+    Both,
+    /// Both sides changed this path differently
+    Conflict,
+}
+
+impl Display for ChangeOrigin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            ChangeOrigin::Ours => "ours",
+            ChangeOrigin::Theirs => "theirs",
+            ChangeOrigin::Both => "both",
+            ChangeOrigin::Conflict => "conflict",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreeWayChange {
+    pub path: Vec<String>,
+    pub origin: ChangeOrigin,
+}
+
+impl ThreeWayChange {
+    pub fn new(path: Vec<String>, origin: ChangeOrigin) -> Self {
+        Self { path, origin }
+    }
+}
+
+impl Display for ThreeWayChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.origin, join_path(&self.path))
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ThreeWayMismatch {
+    pub changes: Vec<ThreeWayChange>,
+}
+
+impl ThreeWayMismatch {
+    pub fn new(changes: Vec<ThreeWayChange>) -> Self {
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|c| c.origin == ChangeOrigin::Conflict)
+    }
+}
@@ -1,30 +1,199 @@
-use std::fmt::{Display, Formatter};
+use serde_json::Value;
+use std::fmt::{Debug, Display, Formatter};
 use thiserror::Error;
 use vg_errortools::FatIOError;
 
-#[derive(Debug, Error)]
+/// All errors of the crate. `Debug` prints the same human readable message as `Display`,
+/// so that an error returned from `main` reads like the other messages of the tool.
+#[derive(Error)]
 pub enum Error {
     #[error("Error opening file: {0}")]
     IOError(#[from] FatIOError),
-    #[error("Error parsing first json: {0}")]
-    JSON(#[from] serde_json::Error),
+    #[error("Error parsing json: {0}")]
+    JSON(#[source] serde_json::Error),
+    #[error("failed to parse {which}: {err}")]
+    Json {
+        which: Which,
+        #[source]
+        err: serde_json::Error,
+    },
+    #[error("{0} is empty")]
+    EmptyInput(Which),
+    #[error("Error parsing json5: {0}")]
+    Json5(#[source] json5::Error),
+    #[error("failed to parse {which}: {err}")]
+    Json5In {
+        which: Which,
+        #[source]
+        err: json5::Error,
+    },
+    #[error("Error decoding file {0}: {1}")]
+    Decode(String, std::io::Error),
+    #[error("Duplicate key in json at: {0}")]
+    DuplicateKey(String),
+    #[error("Pointer {0} does not exist in {1}")]
+    PointerNotFound(String, &'static str),
+    #[error("Error applying patch: {0}")]
+    Patch(String),
+    #[error("Invalid ignore file {0}: {1}")]
+    IgnoreFile(String, String),
+    #[error("Merge patch can't keep null values, they would be removed at: {0}")]
+    AmbiguousMergePatch(String),
+    #[error("IO error: {0}")]
+    Io(#[source] std::io::Error),
+    #[error("Error writing the report: {0}")]
+    Output(#[source] std::io::Error),
+    #[error("Error creating the report file: {0}")]
+    OutputFile(#[source] FatIOError),
+    #[cfg(feature = "watch")]
+    #[error("Error watching files: {0}")]
+    Watch(#[from] notify::Error),
+}
+
+impl Error {
+    /// Attributes a json or json5 syntax error to one of the compared documents, other errors
+    /// are returned unchanged
+    pub fn in_source(self, which: Which) -> Self {
+        match self {
+            Error::JSON(err) => Error::Json { which, err },
+            Error::Json5(err) => Error::Json5In { which, err },
+            other => other,
+        }
+    }
+}
+
+/// One of the read documents: the two sides of a diff, the three of a three-way comparison or
+/// a patch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Which {
+    Left,
+    Right,
+    Base,
+    Mine,
+    Theirs,
+    Patch,
+}
+
+impl Display for Which {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Which::Left => write!(f, "source1"),
+            Which::Right => write!(f, "source2"),
+            Which::Base => write!(f, "base"),
+            Which::Mine => write!(f, "mine"),
+            Which::Theirs => write!(f, "theirs"),
+            Which::Patch => write!(f, "patch"),
+        }
+    }
+}
+
+impl Debug for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
 }
 
 #[derive(Debug)]
 pub enum DiffType {
-    RootMismatch,
+    /// The two documents have different types at the root, e.g. an object and an array
+    RootMismatch {
+        left: JsonType,
+        right: JsonType,
+    },
     LeftExtra,
     RightExtra,
     Mismatch,
+    TypeMismatch,
+    Moved,
 }
 
 impl Display for DiffType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let msg = match self {
-            DiffType::RootMismatch => "Mismatch at root.",
+            DiffType::RootMismatch { left, right } => {
+                return write!(f, "root: {left} != {right}");
+            }
             DiffType::LeftExtra => "Extra on left",
             DiffType::RightExtra => "Extra on right",
-            DiffType::Mismatch => "Mismatched",
+            DiffType::Mismatch => "Value changed",
+            DiffType::TypeMismatch => "Type changed",
+            DiffType::Moved => "Moved",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// Kind of a single structured difference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+    /// An array element that is equal on both sides but sits at another index
+    Moved,
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+            ChangeKind::Moved => "moved",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::str::FromStr for ChangeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "added" => Ok(ChangeKind::Added),
+            "removed" => Ok(ChangeKind::Removed),
+            "changed" => Ok(ChangeKind::Changed),
+            "moved" => Ok(ChangeKind::Moved),
+            _ => Err(format!(
+                "unknown change kind '{s}', expected added, removed, changed or moved"
+            )),
+        }
+    }
+}
+
+/// The JSON type of a value, used to tell type changes apart from value changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonType {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl From<&Value> for JsonType {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => JsonType::Null,
+            Value::Bool(_) => JsonType::Bool,
+            Value::Number(_) => JsonType::Number,
+            Value::String(_) => JsonType::String,
+            Value::Array(_) => JsonType::Array,
+            Value::Object(_) => JsonType::Object,
+        }
+    }
+}
+
+impl Display for JsonType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            JsonType::Null => "null",
+            JsonType::Bool => "bool",
+            JsonType::Number => "number",
+            JsonType::String => "string",
+            JsonType::Array => "array",
+            JsonType::Object => "object",
         };
         write!(f, "{}", msg)
     }
@@ -36,15 +205,22 @@ pub enum ValueType {
         key: String,
         value_left: String,
         value_right: String,
+        type_changed: bool,
     },
 }
 
 impl ValueType {
-    pub fn new_value(key: String, value_left: String, value_right: String) -> Self {
+    pub fn new_value(
+        key: String,
+        value_left: String,
+        value_right: String,
+        type_changed: bool,
+    ) -> Self {
         Self::Value {
             value_right,
             value_left,
             key,
+            type_changed,
         }
     }
     pub fn new_key(key: String) -> Self {
@@ -57,6 +233,16 @@ impl ValueType {
             ValueType::Key(key) => key.as_str(),
         }
     }
+
+    pub fn is_type_change(&self) -> bool {
+        matches!(
+            self,
+            ValueType::Value {
+                type_changed: true,
+                ..
+            }
+        )
+    }
 }
 
 impl Display for ValueType {
@@ -67,9 +253,49 @@ impl Display for ValueType {
                 value_left,
                 key,
                 value_right,
+                ..
             } => {
                 write!(f, "{key}{{{value_left}!={value_right}}}")
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_composable() {
+        fn assert_std_error<E: std::error::Error + Send + Sync + 'static>() {}
+        assert_std_error::<Error>();
+
+        let error = Error::JSON(serde_json::from_str::<Value>("{").unwrap_err());
+        assert_eq!(format!("{error:?}"), error.to_string());
+        let boxed: Box<dyn std::error::Error> =
+            Box::new(Error::PointerNotFound("/a".to_string(), "first json"));
+        assert_eq!(boxed.to_string(), "Pointer /a does not exist in first json");
+    }
+
+    #[test]
+    fn json_errors_name_their_source() {
+        let error =
+            Error::JSON(serde_json::from_str::<Value>("[").unwrap_err()).in_source(Which::Right);
+        assert_eq!(
+            error.to_string(),
+            "failed to parse source2: EOF while parsing a list at line 1 column 1"
+        );
+        assert!(std::error::Error::source(&error).is_some());
+
+        let error =
+            Error::Json5(json5::from_str::<Value>("{a:").unwrap_err()).in_source(Which::Mine);
+        assert!(matches!(
+            error,
+            Error::Json5In {
+                which: Which::Mine,
+                ..
+            }
+        ));
+        assert!(error.to_string().starts_with("failed to parse mine: "));
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use crate::enums::{Error, Which};
+use crate::path_glob::{parse_ignore_rules, PathGlob};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const BOM: char = '\u{feff}';
+
+/// Reads a json file, gzip compressed files (`.gz` or starting with the gzip magic bytes)
+/// are decompressed on the fly. A leading UTF-8 byte order mark is dropped.
+pub fn read_file(path: impl AsRef<Path>) -> Result<String, Error> {
+    let path = path.as_ref();
+    let bytes = vg_errortools::fat_io_wrap_std(path, &std::fs::read)?;
+    let is_gzip = path.extension().is_some_and(|ext| ext == "gz");
+    let content = if is_gzip || bytes.starts_with(&GZIP_MAGIC) {
+        let mut content = String::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut content)
+            .map(|_| content)
+    } else {
+        String::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    };
+    content
+        .map(|content| match content.strip_prefix(BOM) {
+            Some(stripped) => stripped.to_string(),
+            None => content,
+        })
+        .map_err(|e| Error::Decode(path.display().to_string(), e))
+}
+
+/// Opens a json file for reading it piece by piece, with the same gzip detection and byte
+/// order mark handling as `read_file`
+pub fn open_stream(path: impl AsRef<Path>) -> Result<Box<dyn BufRead>, Error> {
+    let path = path.as_ref();
+    let decode_error = |e| Error::Decode(path.display().to_string(), e);
+    let mut reader = BufReader::new(vg_errortools::fat_io_wrap_std(path, &std::fs::File::open)?);
+    let is_gzip = path.extension().is_some_and(|ext| ext == "gz");
+    let starts_gzip = reader
+        .fill_buf()
+        .map_err(decode_error)?
+        .starts_with(&GZIP_MAGIC);
+    let mut reader: Box<dyn BufRead> = if is_gzip || starts_gzip {
+        Box::new(BufReader::new(GzDecoder::new(reader)))
+    } else {
+        Box::new(reader)
+    };
+    let mut bom = [0; 3];
+    BOM.encode_utf8(&mut bom);
+    if reader.fill_buf().map_err(decode_error)?.starts_with(&bom) {
+        reader.consume(bom.len());
+    }
+    Ok(reader)
+}
+
+/// Reads both sources of a comparison
+pub fn read_sources(
+    path_1: impl AsRef<Path>,
+    path_2: impl AsRef<Path>,
+) -> Result<(String, String), Error> {
+    Ok((read_file(path_1)?, read_file(path_2)?))
+}
+
+/// Name of the ignore file that is picked up from the working directory
+pub const IGNORE_FILE: &str = ".jsondiffignore";
+
+/// Reads the path patterns of an ignore file, see `path_glob::parse_ignore_rules`
+pub fn read_ignore_file(path: impl AsRef<Path>) -> Result<Vec<PathGlob>, Error> {
+    parse_ignore_rules(&read_file(&path)?)
+        .map_err(|err| Error::IgnoreFile(path.as_ref().display().to_string(), err))
+}
+
+/// Fails with `Error::EmptyInput` if a source holds nothing but whitespace, which the parser
+/// would only report as an unexpected end of input
+pub fn ensure_not_empty(json: &str, which: Which) -> Result<(), Error> {
+    if json.trim().is_empty() {
+        return Err(Error::EmptyInput(which));
+    }
+    Ok(())
+}
+
+/// Names of the files of two directories, paired by name
+#[derive(Debug, Default, PartialEq)]
+pub struct DirPairs {
+    /// Files present in both directories
+    pub both: Vec<String>,
+    /// Files only in the first directory
+    pub left_only: Vec<String>,
+    /// Files only in the second directory
+    pub right_only: Vec<String>,
+}
+
+/// Pairs the files directly inside two directories by file name, each list is sorted.
+/// Subdirectories are not entered.
+pub fn pair_dir_files(dir_1: impl AsRef<Path>, dir_2: impl AsRef<Path>) -> Result<DirPairs, Error> {
+    let left = vg_errortools::fat_io_wrap_std(dir_1.as_ref(), &file_names)?;
+    let right = vg_errortools::fat_io_wrap_std(dir_2.as_ref(), &file_names)?;
+    let mut pairs = DirPairs::default();
+    for name in &left {
+        if right.contains(name) {
+            pairs.both.push(name.clone());
+        } else {
+            pairs.left_only.push(name.clone());
+        }
+    }
+    pairs.right_only = right.difference(&left).cloned().collect();
+    Ok(pairs)
+}
+
+fn file_names(dir: &Path) -> std::io::Result<BTreeSet<String>> {
+    let mut names = BTreeSet::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            names.insert(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn read_plain_and_gzip_files() {
+        let dir = std::env::temp_dir();
+        let plain = dir.join("json_diff_read_plain.json");
+        let gzip = dir.join("json_diff_read_gzip.json.gz");
+        std::fs::write(&plain, r#"{"a":1}"#).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(br#"{"a":2}"#).unwrap();
+        std::fs::write(&gzip, encoder.finish().unwrap()).unwrap();
+
+        let (d1, d2) = read_sources(&plain, &gzip).unwrap();
+        assert_eq!(d1, r#"{"a":1}"#);
+        assert_eq!(d2, r#"{"a":2}"#);
+    }
+
+    #[test]
+    fn compression_is_detected_per_file() {
+        let dir = std::env::temp_dir();
+        let plain = dir.join("json_diff_mixed_plain.json");
+        // compressed without the .gz extension, found by its magic bytes
+        let gzip = dir.join("json_diff_mixed_gzip.json");
+        std::fs::write(&plain, r#"{"a":1,"b":[1,2]}"#).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(br#"{"a":2,"b":[1,2]}"#).unwrap();
+        std::fs::write(&gzip, encoder.finish().unwrap()).unwrap();
+
+        for (left, right) in [(&plain, &gzip), (&gzip, &plain)] {
+            let (d1, d2) = read_sources(left, right).unwrap();
+            let diff = crate::process::compare_jsons(&d1, &d2, &Default::default(), None);
+            assert_eq!(diff.unwrap().counts().changed, 1);
+        }
+        let mut content = String::new();
+        open_stream(&gzip)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, r#"{"a":2,"b":[1,2]}"#);
+    }
+
+    #[test]
+    fn byte_order_mark_is_stripped() {
+        let dir = std::env::temp_dir();
+        let plain = dir.join("json_diff_read_bom.json");
+        let gzip = dir.join("json_diff_read_bom.json.gz");
+        std::fs::write(&plain, b"\xEF\xBB\xBF{}").unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"\xEF\xBB\xBF{}").unwrap();
+        std::fs::write(&gzip, encoder.finish().unwrap()).unwrap();
+
+        let (d1, d2) = read_sources(&plain, &gzip).unwrap();
+        assert_eq!((d1.as_str(), d2.as_str()), ("{}", "{}"));
+        let diff = crate::process::compare_jsons(&d1, &d2, &Default::default(), None).unwrap();
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn files_are_paired_by_name() {
+        let dir = std::env::temp_dir().join("json_diff_pair_dirs");
+        let (expected, actual) = (dir.join("expected"), dir.join("actual"));
+        for (dir, names) in [
+            (&expected, ["a.json", "b.json"]),
+            (&actual, ["b.json", "c.json"]),
+        ] {
+            std::fs::create_dir_all(dir.join("nested")).unwrap();
+            for name in names {
+                std::fs::write(dir.join(name), "{}").unwrap();
+            }
+        }
+
+        assert_eq!(
+            pair_dir_files(&expected, &actual).unwrap(),
+            DirPairs {
+                both: vec!["b.json".to_string()],
+                left_only: vec!["a.json".to_string()],
+                right_only: vec!["c.json".to_string()],
+            }
+        );
+        assert!(matches!(
+            pair_dir_files(&expected, dir.join("missing")),
+            Err(Error::IOError(_))
+        ));
+    }
+
+    #[test]
+    fn streams_are_decompressed_without_bom() {
+        let dir = std::env::temp_dir();
+        let gzip = dir.join("json_diff_stream_bom.json.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"\xEF\xBB\xBF[1]").unwrap();
+        std::fs::write(&gzip, encoder.finish().unwrap()).unwrap();
+
+        let mut content = String::new();
+        open_stream(&gzip)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "[1]");
+    }
+
+    #[test]
+    fn read_errors() {
+        let dir = std::env::temp_dir();
+        let corrupt = dir.join("json_diff_read_corrupt.json.gz");
+        std::fs::write(&corrupt, "not gzip").unwrap();
+        assert!(matches!(read_file(&corrupt), Err(Error::Decode(_, _))));
+        assert!(matches!(
+            read_file(dir.join("json_diff_does_not_exist.json")),
+            Err(Error::IOError(_))
+        ));
+    }
+}
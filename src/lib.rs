@@ -1,3 +1,13 @@
+pub mod canonical;
 pub mod ds;
 pub mod enums;
+pub mod input;
+pub mod options;
+pub mod parse;
+pub mod patch;
+pub mod path_glob;
 pub mod process;
+pub mod render;
+pub mod stream;
+
+pub use process::{diff_str, diff_values};
@@ -1,18 +1,118 @@
+use std::fmt::Display;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use clap::error::ErrorKind;
+use clap::CommandFactory;
 use clap::Parser;
 use clap::Subcommand;
+use clap::ValueEnum;
+use regex::Regex;
 
-use json_diff::enums::Error;
-use json_diff::{ds::mismatch::Mismatch, process::compare_jsons};
+use json_diff::canonical::{canonicalize, sort_keys_with};
+use json_diff::ds::mismatch::{DiffCounts, Mismatch};
+use json_diff::ds::three_way::ThreeWayMismatch;
+use json_diff::enums::{ChangeKind, Error, Which};
+use json_diff::input::{
+    ensure_not_empty, open_stream, pair_dir_files, read_file, read_ignore_file, read_sources,
+    IGNORE_FILE,
+};
+#[cfg(feature = "unicode")]
+use json_diff::options::UnicodeForm;
+use json_diff::options::{ArrayAlgorithm, DiffOptions, Tolerance, ToleranceMode};
+use json_diff::parse::{parse_json, parse_json5, SourcePositions};
+use json_diff::patch::{apply_patch, parse_patch, to_json_patch, to_merge_patch};
+use json_diff::path_glob::{self, PathGlob};
+use json_diff::process::{
+    common_value, match_json_three_way_filtered, match_json_with_options, select_subtrees,
+    unchanged_leaves,
+};
+use json_diff::render;
+use json_diff::render::{TextOptions, Theme};
+use json_diff::stream::match_array_streams;
+use serde_json::Value;
 
 #[derive(Subcommand, Clone)]
 /// Input selection
 enum Mode {
     /// File input
     #[clap(short_flag = 'f')]
-    File { file_1: String, file_2: String },
+    File {
+        file_1: String,
+        file_2: String,
+        #[cfg(feature = "watch")]
+        #[clap(long)]
+        /// keep running and compare again whenever one of the files changes
+        watch: bool,
+        #[clap(long)]
+        /// compare two top-level arrays element by element while reading them, for files too
+        /// large to load; elements are only compared by position
+        stream: bool,
+    },
     /// Read from CLI
     #[clap(short_flag = 'd')]
     Direct { json_1: String, json_2: String },
+    /// Compare several files against the first one
+    #[clap(short_flag = 'm')]
+    Multi {
+        base: String,
+        #[clap(required = true)]
+        others: Vec<String>,
+    },
+    /// Compare the files of two directories paired by name, files only in one are reported
+    Dir { dir_1: String, dir_2: String },
+    /// Three-way comparison of two files against a common base, fails on conflicts
+    ThreeWay {
+        base: String,
+        mine: String,
+        theirs: String,
+    },
+    /// Apply an RFC 6902 json patch file to a json file and print the result
+    Apply { base: String, patch: String },
+    /// Print every leaf of a single json file as a `<json pointer> = <value>` line
+    Flatten { file: String },
+    /// Print a completion script for the given shell
+    Completions { shell: clap_complete::Shell },
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+/// Output format of the report
+enum Format {
+    /// One line per difference followed by a summary
+    #[default]
+    Text,
+    /// `-`/`+` lines like `git diff`, removals red and additions green unless colors are off
+    Unified,
+    /// Self-contained HTML page with a table of all differences
+    Html,
+    /// GitHub-flavored Markdown tables
+    Markdown,
+    /// Indented tree of the paths with differences
+    Tree,
+    /// Left and right values in two columns below each path
+    SideBySide,
+    /// One json object per difference and line, paths as JSON pointers
+    Jsonl,
+    /// Only the similarity of both documents, from 0 (nothing in common) to 1 (equal)
+    Score,
+    /// RFC 7386 merge patch that turns the first document into the second
+    MergePatch,
+    /// RFC 6902 patch of add, remove and replace operations that turns the first document into
+    /// the second
+    JsonPatch,
+    /// One json object with the number of differences per kind, equal leaves and similarity
+    SummaryJson,
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+/// Syntax of the input documents
+enum InputFormat {
+    /// Strict json
+    #[default]
+    Json,
+    /// JSON5 with comments, trailing commas and unquoted keys
+    Json5,
 }
 
 #[derive(Parser)]
@@ -24,36 +124,1026 @@ struct Args {
     /// deep-sort arrays before comparing
     sort_arrays: bool,
 
+    #[clap(long = "array-algo", default_value = "myers")]
+    /// how array elements are aligned: myers, positional (index by index) or lcs
+    array_algorithm: ArrayAlgorithm,
+
+    #[clap(long)]
+    /// compare arrays longer than this as multisets: equal elements match anywhere, the rest
+    /// is added or removed; faster on huge arrays but element positions are not aligned
+    array_threshold: Option<usize>,
+
+    #[clap(long, visible_alias = "any")]
+    /// stop comparing at the first difference and only report that one, for a quick
+    /// equal-or-not check through the exit code
+    stop_at_first: bool,
+
+    #[clap(long)]
+    /// report array elements that only changed their position as moved instead of as
+    /// removed and added
+    detect_moves: bool,
+
+    #[clap(long)]
+    /// sort arrays that only contain scalars before comparing, other arrays keep their order
+    sort_scalar_arrays: bool,
+
+    #[clap(long, conflicts_with_all = ["sort_arrays", "sort_scalar_arrays", "sort_object_arrays"])]
+    /// drop array elements equal to an earlier element before comparing, for arrays that are
+    /// sets but keep their order
+    array_dedup: bool,
+
+    #[clap(long = "array-sort-objects")]
+    /// sort arrays that only contain objects by the canonical json of each object before
+    /// comparing, so reordered records are no difference
+    sort_object_arrays: bool,
+
+    #[clap(long = "ignore-key-regex")]
+    /// ignore object keys whose name matches this regex at any depth, can be repeated;
+    /// matches single key names, not full paths
+    ignore_key_regex: Vec<Regex>,
+
+    #[clap(long = "ignore-path")]
+    /// drop differences at or below dotted paths matching this pattern, can be repeated;
+    /// * matches one key or array index, ** any number of them, e.g. items.*.updated_at
+    ignore_path: Vec<PathGlob>,
+
+    #[clap(long = "only-path")]
+    /// only report differences at or below dotted paths matching this pattern, can be
+    /// repeated; --ignore-path still drops paths within them
+    only_path: Vec<PathGlob>,
+
+    #[clap(long)]
+    /// read more --ignore-path patterns from this file, one per line with # comments;
+    /// defaults to .jsondiffignore in the working directory if that exists
+    ignore_file: Option<String>,
+
+    #[clap(long)]
+    /// treat a key that is null on one side and missing on the other as equal
+    null_equals_missing: bool,
+
+    #[clap(long)]
+    /// match object keys that only differ in case, e.g. UserName and username
+    ignore_key_case: bool,
+
+    #[clap(long)]
+    /// numbers are equal if they have the same value in any notation, e.g. 1.0, 1e0 and 1,
+    /// compared exactly without rounding big integers
+    numeric_exact: bool,
+
+    #[clap(long)]
+    /// accept numbers at dotted paths matching the pattern that differ by at most epsilon,
+    /// e.g. **.price=0.01; can be repeated, the last matching rule wins
+    tolerance: Vec<Tolerance>,
+
+    #[clap(long, default_value = "absolute")]
+    /// apply tolerances as absolute difference or relative to the larger number:
+    /// absolute or relative
+    tolerance_mode: ToleranceMode,
+
+    #[clap(long)]
+    /// treat {}, [] and null as equal to each other
+    empty_equals_null: bool,
+
+    #[clap(long)]
+    /// treat a string as equal to the number or bool it spells, e.g. "1" and 1
+    coerce_scalars: bool,
+
+    #[clap(long)]
+    /// only compare the structure: key presence, array lengths and value types
+    keys_only: bool,
+
+    #[clap(long)]
+    /// only report changed values at keys present in both jsons, ignore added and removed keys
+    values_only: bool,
+
+    #[clap(long)]
+    /// check that the first json is contained in the second, keys only in the second are fine
+    subset: bool,
+
+    #[clap(long)]
+    /// replace parts of string values matching this regex by a placeholder on both sides
+    /// before comparing, can be repeated; numbers and other values are not masked
+    mask: Vec<Regex>,
+
+    #[cfg(feature = "unicode")]
+    #[clap(long)]
+    /// normalize strings to this unicode form (nfc or nfd) before comparing them
+    unicode_normalize: Option<UnicodeForm>,
+
+    #[clap(long)]
+    /// compare two differing strings that both contain json as parsed json
+    parse_embedded_json: bool,
+
+    #[clap(long)]
+    /// canonicalize both jsons first (RFC 8785 style), so 1.0 equals 1 and key order is ignored
+    canonical: bool,
+
+    #[clap(long)]
+    /// print both parsed jsons pretty printed with sorted keys before the report
+    dump_normalized: bool,
+
+    #[clap(long)]
+    /// with --dump-normalized, sort the keys of objects whose keys are all integers by their
+    /// value, so "2" comes before "10"
+    numeric_key_sort: bool,
+
+    #[clap(long)]
+    /// print the parts that are equal on both sides as pretty printed json instead of the
+    /// report, `null` if nothing is equal
+    emit_common: bool,
+
+    #[clap(long)]
+    /// only report differences of this kind: added, removed, changed or moved; can be repeated
+    only: Vec<ChangeKind>,
+
+    #[clap(long, value_delimiter = ',')]
+    /// only fail on differences of these kinds, e.g. changed,removed to allow additions;
+    /// default: any difference fails
+    fail_on: Vec<ChangeKind>,
+
+    #[clap(long)]
+    /// only compare the subtree at this JSON pointer, e.g. /data/items
+    at: Option<String>,
+
     #[clap(short, long, default_value_t = 20)]
     /// truncate keys with more chars then this parameter
     truncation_length: usize,
+
+    #[clap(short, long)]
+    /// write the report to this file instead of stdout
+    output: Option<String>,
+
+    #[clap(long, value_name = "PATH|-")]
+    /// also write the differences as json lines like --format jsonl to this file, or to stderr
+    /// for -, next to the normal report; written even with --quiet
+    report_json: Option<String>,
+
+    #[clap(short, long)]
+    /// print nothing, only report the result through the exit code
+    quiet: bool,
+
+    #[clap(long)]
+    /// in the text format, count the differences per top-level key instead of listing them,
+    /// e.g. users: +3 -1 ~2, followed by the number of equal leaves
+    stat: bool,
+
+    #[clap(long)]
+    /// also list the leaves that are equal on both sides in the text format
+    show_unchanged: bool,
+
+    #[clap(long)]
+    /// follow changed values with their json type in the text format, e.g. 5 (number)
+    show_types: bool,
+
+    #[clap(long)]
+    /// pretty print changed objects and arrays on their own lines in the text format
+    pretty_values: bool,
+
+    #[clap(long)]
+    /// don't pad the paths of changed values in the text format, by default their values
+    /// start in the same column
+    no_align: bool,
+
+    #[clap(long)]
+    /// follow changes between two numbers in the text format with their difference, e.g.
+    /// (+5), colored like added keys for increases and like removed keys for decreases
+    number_delta: bool,
+
+    #[clap(long)]
+    /// cut off values longer than this many chars in the text format
+    max_value_len: Option<usize>,
+
+    #[clap(long, conflicts_with = "at")]
+    /// append the line of each difference in its source document to the text report
+    line_numbers: bool,
+
+    #[clap(long)]
+    /// print at most this many differences in the text format, the summary still counts all
+    max_diffs: Option<usize>,
+
+    #[clap(
+        long,
+        default_value = "->",
+        allow_hyphen_values = true,
+        value_parser = clap::builder::NonEmptyStringValueParser::new()
+    )]
+    /// join path segments with this separator in the text format, e.g. . or /
+    path_separator: String,
+
+    #[clap(long, value_enum, default_value_t = Format::Text)]
+    /// output format of the report
+    format: Format,
+
+    #[clap(long)]
+    /// explain the colors in a line above colored text and tree reports
+    legend: bool,
+
+    #[clap(long)]
+    /// fail instead of warning when the merge-patch format can't keep a null value
+    strict_merge_patch: bool,
+
+    #[clap(long, default_value_t = 80)]
+    /// total width of the side-by-side format, values wrap inside their column
+    width: usize,
+
+    #[clap(long, env = "JSON_DIFF_THEME")]
+    /// override report colors, e.g. left-only=magenta,changed-right=yellow;
+    /// categories are left-only, right-only, changed-left and changed-right
+    theme: Option<Theme>,
+
+    #[clap(long, value_enum, default_value_t = InputFormat::Json)]
+    /// syntax of the input documents
+    input: InputFormat,
+
+    #[clap(long)]
+    /// fail on objects with duplicate keys instead of keeping the last value
+    strict_duplicate_keys: bool,
+}
+
+impl Args {
+    /// Colors are only used when the report goes to a terminal
+    fn color_theme(&self) -> Option<Theme> {
+        let to_terminal = self.output.is_none() && !self.quiet && std::io::stdout().is_terminal();
+        to_terminal.then(|| self.theme.unwrap_or_default())
+    }
+
+    /// Adds the patterns of the ignore file to those given with `--ignore-path`
+    fn load_ignore_file(&mut self) -> Result<(), Error> {
+        let path = match &self.ignore_file {
+            Some(path) => Path::new(path),
+            None if Path::new(IGNORE_FILE).is_file() => Path::new(IGNORE_FILE),
+            None => return Ok(()),
+        };
+        self.ignore_path.extend(read_ignore_file(path)?);
+        Ok(())
+    }
+
+    /// Whether differences of these counts fail the comparison, see `--fail-on`
+    fn fails(&self, counts: DiffCounts) -> bool {
+        if self.fail_on.is_empty() {
+            counts != DiffCounts::default()
+        } else {
+            self.fail_on.iter().any(|kind| counts.of(*kind) > 0)
+        }
+    }
+
+    /// Whether a difference at `path` is reported with `--ignore-path` and `--only-path`
+    fn keeps_path(&self, path: &[String]) -> bool {
+        let allowed = self.only_path.is_empty() || path_glob::selects(&self.only_path, path);
+        allowed && !path_glob::ignores(&self.ignore_path, path)
+    }
+
+    fn diff_options(&self) -> DiffOptions {
+        let options = DiffOptions::default()
+            .sort_arrays(self.sort_arrays)
+            .sort_scalar_arrays(self.sort_scalar_arrays)
+            .sort_object_arrays(self.sort_object_arrays)
+            .array_dedup(self.array_dedup)
+            .ignore_keys(self.ignore_key_regex.clone())
+            .null_equals_missing(self.null_equals_missing)
+            .ignore_key_case(self.ignore_key_case)
+            .keys_only(self.keys_only)
+            .subset(self.subset)
+            .parse_embedded_json(self.parse_embedded_json)
+            .masks(self.mask.clone())
+            .empty_equals_null(self.empty_equals_null)
+            .coerce_scalars(self.coerce_scalars)
+            .numeric_exact(self.numeric_exact)
+            .tolerances(self.tolerance.clone())
+            .tolerance_mode(self.tolerance_mode)
+            .array_algorithm(self.array_algorithm)
+            .array_threshold(self.array_threshold)
+            .detect_moves(self.detect_moves)
+            .stop_at_first(self.stop_at_first);
+        #[cfg(feature = "unicode")]
+        let options = options.unicode_normalize(self.unicode_normalize);
+        options
+    }
+
+    fn read_json(&self, json: &str) -> Result<Value, Error> {
+        match self.input {
+            InputFormat::Json => parse_json(json, self.strict_duplicate_keys),
+            InputFormat::Json5 => parse_json5(json, self.strict_duplicate_keys),
+        }
+    }
+
+    /// Parses a document that is going to be compared
+    fn read_compared(&self, json: &str) -> Result<Value, Error> {
+        let value = self.read_json(json)?;
+        Ok(if self.canonical {
+            canonicalize(value)
+        } else {
+            value
+        })
+    }
+
+    /// Parses both documents and hands the compared (sub)trees to `f`
+    fn with_documents<T>(
+        &self,
+        json_1: &str,
+        json_2: &str,
+        f: impl FnOnce(&Value, &Value) -> T,
+    ) -> Result<T, Error> {
+        ensure_not_empty(json_1, Which::Left)?;
+        ensure_not_empty(json_2, Which::Right)?;
+        let value_1 = self
+            .read_compared(json_1)
+            .map_err(|err| err.in_source(Which::Left))?;
+        let value_2 = self
+            .read_compared(json_2)
+            .map_err(|err| err.in_source(Which::Right))?;
+        let (value_1, value_2) = match &self.at {
+            Some(pointer) => select_subtrees(&value_1, &value_2, pointer)?,
+            None => (&value_1, &value_2),
+        };
+        Ok(f(value_1, value_2))
+    }
+
+    fn compare(&self, json_1: &str, json_2: &str) -> Result<Mismatch, Error> {
+        let count_equal = self.stat || matches!(self.format, Format::Score | Format::SummaryJson);
+        let options = self.diff_options().count_equal(count_equal);
+        let path_filtered = !self.only_path.is_empty() || !self.ignore_path.is_empty();
+        let mismatch = self.with_documents(json_1, json_2, |value_1, value_2| {
+            let mismatch = match_json_with_options(value_1, value_2, &options);
+            if !(count_equal && path_filtered) {
+                return mismatch;
+            }
+            // equal leaves at paths that are left out don't count either
+            let equal_leaves = unchanged_leaves(value_1, value_2, &options)
+                .iter()
+                .filter(|path| self.keeps_path(path))
+                .count();
+            mismatch.with_equal_leaves(equal_leaves)
+        })?;
+        Ok(self.filter(mismatch))
+    }
+
+    /// The first option given that `file --stream` can't honor, since it never holds a whole
+    /// document
+    fn unsupported_by_stream(&self) -> Option<&'static str> {
+        if !matches!(self.cmd, Mode::File { stream: true, .. }) {
+            return None;
+        }
+        [
+            (!matches!(self.format, Format::Text), "--format"),
+            (!matches!(self.input, InputFormat::Json), "--input"),
+            (self.at.is_some(), "--at"),
+            (self.canonical, "--canonical"),
+            (self.dump_normalized, "--dump-normalized"),
+            (self.emit_common, "--emit-common"),
+            (self.stat, "--stat"),
+            (self.show_unchanged, "--show-unchanged"),
+            (self.line_numbers, "--line-numbers"),
+        ]
+        .into_iter()
+        .find_map(|(given, flag)| given.then_some(flag))
+    }
+
+    /// Drops the differences hidden by `--ignore-path`, `--only-path`, `--values-only` and `--only`
+    fn filter(&self, mismatch: Mismatch) -> Mismatch {
+        let mismatch = mismatch.retain(|path| self.keeps_path(path));
+        let mismatch = if self.values_only {
+            mismatch.values_only()
+        } else {
+            mismatch
+        };
+        if self.only.is_empty() {
+            mismatch
+        } else {
+            mismatch.only(&self.only)
+        }
+    }
+
+    /// The subtree of a three-way document selected by `--at`
+    fn select<'a>(&self, value: &'a Value, name: &'static str) -> Result<&'a Value, Error> {
+        match &self.at {
+            Some(pointer) => value
+                .pointer(pointer)
+                .ok_or_else(|| Error::PointerNotFound(pointer.clone(), name)),
+            None => Ok(value),
+        }
+    }
+}
+
+/// Exit code when the documents are equal
+const EXIT_EQUAL: u8 = 0;
+/// Exit code when the documents differ
+const EXIT_DIFFERENT: u8 = 1;
+/// Exit code when an input could not be read or parsed
+const EXIT_INPUT_ERROR: u8 = 2;
+/// Exit code for failures of the tool itself, including panics and reports that can't be written
+const EXIT_INTERNAL_ERROR: u8 = 3;
+
+fn main() -> ExitCode {
+    let mut args = Args::parse();
+    if let Some(flag) = args.unsupported_by_stream() {
+        Args::command()
+            .bin_name("json_diff")
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("the argument '--stream' cannot be used with '{flag}'"),
+            )
+            .exit();
+    }
+    let result = std::panic::catch_unwind(move || {
+        args.load_ignore_file()?;
+        run(&args)
+    });
+    match result {
+        Ok(Ok(code)) => ExitCode::from(code),
+        Ok(Err(err)) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(exit_code(&err))
+        }
+        // the panic message was already printed by the panic hook
+        Err(_) => ExitCode::from(EXIT_INTERNAL_ERROR),
+    }
+}
+
+fn exit_code(err: &Error) -> u8 {
+    match err {
+        Error::IOError(_)
+        | Error::JSON(_)
+        | Error::Json { .. }
+        | Error::EmptyInput(_)
+        | Error::Json5(_)
+        | Error::Json5In { .. }
+        | Error::IgnoreFile(..)
+        | Error::Decode(..)
+        | Error::DuplicateKey(_)
+        | Error::PointerNotFound(..)
+        | Error::Patch(_)
+        | Error::AmbiguousMergePatch(_)
+        | Error::Io(_) => EXIT_INPUT_ERROR,
+        Error::Output(_) | Error::OutputFile(_) => EXIT_INTERNAL_ERROR,
+        #[cfg(feature = "watch")]
+        Error::Watch(_) => EXIT_INTERNAL_ERROR,
+    }
+}
+
+/// Where a run writes to: the report goes to `main`, the `--report-json` sink additionally
+/// gets the differences of every comparison as json lines
+struct Output {
+    main: Box<dyn Write>,
+    report_json: Option<Box<dyn Write>>,
+    /// Fields naming the compared files, added to the json lines of modes comparing several
+    /// pairs of files
+    sources: serde_json::Map<String, Value>,
 }
 
-fn main() -> Result<(), Error> {
-    let args = Args::parse();
-    let (json_1, json_2) = match args.cmd {
-        Mode::Direct { json_2, json_1 } => (json_1, json_2),
-        Mode::File { file_2, file_1 } => {
-            let d1 = vg_errortools::fat_io_wrap_std(file_1, &std::fs::read_to_string)?;
-            let d2 = vg_errortools::fat_io_wrap_std(file_2, &std::fs::read_to_string)?;
-            (d1, d2)
+impl Output {
+    fn new(main: Box<dyn Write>, report_json: Option<Box<dyn Write>>) -> Self {
+        Self {
+            main,
+            report_json,
+            sources: serde_json::Map::new(),
+        }
+    }
+
+    /// Names the files whose differences are reported next, as `left_file` and `right_file`
+    fn compare_files(&mut self, left: impl Display, right: impl Display) {
+        self.sources
+            .insert("left_file".into(), left.to_string().into());
+        self.sources
+            .insert("right_file".into(), right.to_string().into());
+    }
+
+    /// Writes the differences to the `--report-json` sink, if there is one
+    fn report(&mut self, mismatch: &Mismatch) -> Result<(), Error> {
+        if let Some(report_json) = &mut self.report_json {
+            render::jsonl_with(mismatch, &self.sources, report_json).map_err(Error::Output)?;
+        }
+        Ok(())
+    }
+
+    /// Writes to the report like `Write::write_fmt`, failures are `Error::Output`. `write!`
+    /// and `writeln!` on an `Output` call this method instead of the one of `Write`.
+    fn write_fmt(&mut self, args: std::fmt::Arguments) -> Result<(), Error> {
+        Write::write_fmt(self, args).map_err(Error::Output)
+    }
+
+    /// Flushes both sinks, see `write_fmt`
+    fn finish(&mut self) -> Result<(), Error> {
+        self.flush().map_err(Error::Output)
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.main.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.report_json {
+            Some(report_json) => report_json.flush().and_then(|_| self.main.flush()),
+            None => self.main.flush(),
+        }
+    }
+}
+
+/// Runs the selected mode, returns the exit code
+fn run(args: &Args) -> Result<u8, Error> {
+    let create = |path: &String| -> Result<Box<dyn Write>, Error> {
+        match vg_errortools::fat_io_wrap_std(path, &std::fs::File::create) {
+            Ok(file) => Ok(Box::new(file)),
+            Err(err) => Err(Error::OutputFile(err)),
         }
     };
+    let main: Box<dyn Write> = match &args.output {
+        _ if args.quiet => Box::new(std::io::sink()),
+        Some(path) => create(path)?,
+        None => Box::new(std::io::stdout()),
+    };
+    let report_json: Option<Box<dyn Write>> = match &args.report_json {
+        Some(path) if path == "-" => Some(Box::new(std::io::stderr())),
+        Some(path) => Some(create(path)?),
+        None => None,
+    };
+    let mut output = Output::new(main, report_json);
+    run_mode(&mut output, args)
+}
 
-    let mismatch = compare_jsons(&json_1, &json_2, args.sort_arrays)?;
+/// Compares the inputs of the selected mode and writes the report to `output`, returns the exit
+/// code
+fn run_mode(output: &mut Output, args: &Args) -> Result<u8, Error> {
+    if let (true, Some(theme), Format::Text | Format::Tree) =
+        (args.legend, args.color_theme(), args.format)
+    {
+        writeln!(output, "{}", render::legend(&theme))?;
+    }
+    // the worst exit code of comparisons that failed without stopping the others
+    let mut failure = None;
+    let comparison_result = match &args.cmd {
+        Mode::Direct { json_2, json_1 } => check_diffs(output, args, json_1, json_2)?,
+        #[cfg(feature = "watch")]
+        Mode::File {
+            file_1,
+            file_2,
+            watch: true,
+            ..
+        } => {
+            watch(output, args, file_1, file_2)?;
+            true
+        }
+        Mode::File {
+            file_1,
+            file_2,
+            stream: true,
+            ..
+        } => check_streams(output, args, file_1, file_2)?,
+        Mode::File { file_2, file_1, .. } => {
+            let (d1, d2) = read_sources(file_1, file_2)?;
+            check_diffs(output, args, &d1, &d2)?
+        }
+        Mode::Multi { base, others } => {
+            let base_json = read_file(base)?;
+            let mut all_good = true;
+            for other in others {
+                writeln!(output, "Comparing {base} with {other}")?;
+                output.compare_files(base, other);
+                let compared = read_file(other)
+                    .and_then(|other_json| check_diffs(output, args, &base_json, &other_json));
+                match compared {
+                    Ok(equal) => all_good &= equal,
+                    // a file that can't be compared is reported and the others are compared
+                    // still, unless the report itself can't be written
+                    Err(err @ Error::Output(_)) => return Err(err),
+                    Err(err) => {
+                        eprintln!("Error: {other}: {err}");
+                        failure = failure.max(Some(exit_code(&err)));
+                    }
+                }
+            }
+            all_good
+        }
+        Mode::Dir { dir_1, dir_2 } => {
+            let pairs = pair_dir_files(dir_1, dir_2)?;
+            let mut all_good = pairs.left_only.is_empty() && pairs.right_only.is_empty();
+            for name in &pairs.both {
+                let (file_1, file_2) = (Path::new(dir_1).join(name), Path::new(dir_2).join(name));
+                writeln!(
+                    output,
+                    "Comparing {} with {}",
+                    file_1.display(),
+                    file_2.display()
+                )?;
+                output.compare_files(file_1.display(), file_2.display());
+                let (d1, d2) = read_sources(&file_1, &file_2)?;
+                all_good &= check_diffs(output, args, &d1, &d2)?;
+            }
+            for name in &pairs.left_only {
+                writeln!(output, "Only in {dir_1}: {name}")?;
+            }
+            for name in &pairs.right_only {
+                writeln!(output, "Only in {dir_2}: {name}")?;
+            }
+            all_good
+        }
+        Mode::ThreeWay { base, mine, theirs } => {
+            let read = |path: &str, which: Which| {
+                let json = read_file(path)?;
+                ensure_not_empty(&json, which)?;
+                args.read_compared(&json)
+                    .map_err(|err| err.in_source(which))
+            };
+            let base = read(base, Which::Base)?;
+            let mine = read(mine, Which::Mine)?;
+            let theirs = read(theirs, Which::Theirs)?;
+            let result = match_json_three_way_filtered(
+                args.select(&base, "base")?,
+                args.select(&mine, "mine")?,
+                args.select(&theirs, "theirs")?,
+                &args.diff_options(),
+                |mismatch| args.filter(mismatch),
+            );
+            check_three_way(output, result)?
+        }
+        Mode::Apply { base, patch } => {
+            let mut base = args
+                .read_json(&read_file(base)?)
+                .map_err(|err| err.in_source(Which::Base))?;
+            apply_patch(&mut base, &parse_patch(&read_file(patch)?)?)?;
+            writeln!(output, "{base:#}")?;
+            true
+        }
+        Mode::Completions { shell } => {
+            clap_complete::generate(*shell, &mut Args::command(), "json_diff", output);
+            true
+        }
+        Mode::Flatten { file } => {
+            for line in render::flatten(&args.read_compared(&read_file(file)?)?) {
+                writeln!(output, "{line}")?;
+            }
+            true
+        }
+    };
+    output.finish()?;
+    Ok(match (failure, comparison_result) {
+        (Some(code), _) => code,
+        (None, true) => EXIT_EQUAL,
+        (None, false) => EXIT_DIFFERENT,
+    })
+}
 
-    let comparison_result = check_diffs(mismatch)?;
-    if !comparison_result {
-        std::process::exit(1);
+fn check_diffs(
+    output: &mut Output,
+    args: &Args,
+    json_1: &str,
+    json_2: &str,
+) -> Result<bool, Error> {
+    if args.dump_normalized {
+        let (value_1, value_2) = args.with_documents(json_1, json_2, |value_1, value_2| {
+            let numeric = args.numeric_key_sort;
+            (
+                sort_keys_with(value_1.clone(), numeric),
+                sort_keys_with(value_2.clone(), numeric),
+            )
+        })?;
+        writeln!(output, "First json:")?;
+        writeln!(output, "{value_1:#}")?;
+        writeln!(output, "Second json:")?;
+        writeln!(output, "{value_2:#}")?;
     }
-    Ok(())
+    let result = args.compare(json_1, json_2)?;
+    output.report(&result)?;
+    if args.emit_common {
+        let options = args.diff_options();
+        let common = args.with_documents(json_1, json_2, |value_1, value_2| {
+            common_value(value_1, value_2, &options)
+        })?;
+        let common = common.unwrap_or(Value::Null);
+        writeln!(output, "{common:#}")?;
+        return Ok(!args.fails(result.counts()));
+    }
+    match args.format {
+        Format::Text => {
+            let positions = args
+                .line_numbers
+                .then(|| (SourcePositions::of(json_1), SourcePositions::of(json_2)));
+            let options = TextOptions {
+                theme: args.color_theme(),
+                pretty_values: args.pretty_values,
+                max_value_len: args.max_value_len,
+                positions: positions.as_ref().map(|(l, r)| (l, r)),
+                path_separator: Some(&args.path_separator),
+                show_types: args.show_types,
+                align_values: !args.no_align,
+                number_delta: args.number_delta,
+            };
+            let lines = if args.stat {
+                render::stat(&result, &options)
+            } else {
+                render::text(&result, &options)
+            };
+            let shown = args.max_diffs.unwrap_or(lines.len()).min(lines.len());
+            for line in &lines[..shown] {
+                writeln!(output, "{line}")?;
+            }
+            if shown < lines.len() {
+                writeln!(output, "... and {} more", lines.len() - shown)?;
+            }
+            if args.stat {
+                writeln!(output, "{} equal leaves", result.equal_leaves)?;
+            }
+            if args.show_unchanged {
+                let diff_options = args.diff_options();
+                let mut paths = args.with_documents(json_1, json_2, |value_1, value_2| {
+                    unchanged_leaves(value_1, value_2, &diff_options)
+                })?;
+                paths.retain(|path| args.keeps_path(path));
+                for line in render::unchanged(&paths, &options) {
+                    writeln!(output, "{line}")?;
+                }
+            }
+            writeln!(output, "{}", result.counts())?;
+        }
+        Format::Unified => {
+            for line in render::unified(&result, args.color_theme().as_ref()) {
+                writeln!(output, "{line}")?;
+            }
+        }
+        Format::Html => write!(output, "{}", render::html(&result))?,
+        Format::Markdown => write!(output, "{}", render::markdown(&result))?,
+        Format::SideBySide => {
+            for line in render::side_by_side(&result, args.width) {
+                writeln!(output, "{line}")?;
+            }
+        }
+        Format::Jsonl => render::jsonl(&result, output).map_err(Error::Output)?,
+        Format::Tree => {
+            for line in render::tree(&result, args.color_theme().as_ref()) {
+                writeln!(output, "{line}")?;
+            }
+        }
+        Format::Score => writeln!(output, "{:.4}", result.similarity())?,
+        Format::MergePatch => {
+            let merge = args.with_documents(json_1, json_2, to_merge_patch)?;
+            if !merge.ambiguous_nulls.is_empty() {
+                let paths = merge.ambiguous_nulls.join(", ");
+                if args.strict_merge_patch {
+                    return Err(Error::AmbiguousMergePatch(paths));
+                }
+                if !args.quiet {
+                    eprintln!("warning: the merge patch removes these keys instead of setting them to null: {paths}");
+                }
+            }
+            writeln!(output, "{:#}", merge.patch)?;
+        }
+        Format::JsonPatch => {
+            let entries = result.diffs();
+            let patch = args.with_documents(json_1, json_2, |_, value_2| {
+                to_json_patch(&entries, value_2)
+            })??;
+            writeln!(output, "{:#}", serde_json::json!(patch))?;
+        }
+        Format::SummaryJson => writeln!(output, "{}", render::summary(&result))?,
+    }
+    Ok(!args.fails(result.counts()))
+}
+
+/// Compares both files again after every change until the process is stopped. A file that is
+/// missing for a moment, as during the save of many editors, is reported and waited for.
+#[cfg(feature = "watch")]
+fn watch(output: &mut Output, args: &Args, file_1: &str, file_2: &str) -> Result<(), Error> {
+    use notify::{RecursiveMode, Watcher};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let files = [Path::new(file_1), Path::new(file_2)];
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    // editors often replace a file on save, which would end a watch on the file itself
+    for file in files {
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    let is_watched = |event: &notify::Event| {
+        event.paths.iter().any(|path| {
+            files
+                .iter()
+                .any(|file| path.file_name() == file.file_name())
+        })
+    };
+
+    loop {
+        write!(output, "\x1B[2J\x1B[H")?;
+        match read_sources(file_1, file_2) {
+            Ok((d1, d2)) => match check_diffs(output, args, &d1, &d2) {
+                Ok(_) => {}
+                Err(err) => writeln!(output, "{err}")?,
+            },
+            Err(err) => writeln!(output, "{err}")?,
+        }
+        output.finish()?;
+
+        loop {
+            match events.recv() {
+                Ok(event) => {
+                    if is_watched(&event?) {
+                        break;
+                    }
+                }
+                Err(_) => return Ok(()),
+            }
+        }
+        // a save usually fires several events, compare once they settled
+        while events.recv_timeout(Duration::from_millis(100)).is_ok() {}
+    }
+}
+
+/// Compares two files holding top-level arrays in the text format without loading them
+fn check_streams(
+    output: &mut Output,
+    args: &Args,
+    file_1: &str,
+    file_2: &str,
+) -> Result<bool, Error> {
+    let options = TextOptions {
+        theme: args.color_theme(),
+        pretty_values: args.pretty_values,
+        max_value_len: args.max_value_len,
+        positions: None,
+        path_separator: Some(&args.path_separator),
+        show_types: args.show_types,
+        align_values: !args.no_align,
+        number_delta: args.number_delta,
+    };
+    let mut counts = DiffCounts::default();
+    let (mut shown, mut hidden) = (0, 0);
+    match_array_streams(
+        open_stream(file_1)?,
+        open_stream(file_2)?,
+        &args.diff_options(),
+        |mismatch| {
+            let mismatch = args.filter(mismatch);
+            let element = mismatch.counts();
+            counts.added += element.added;
+            counts.removed += element.removed;
+            counts.changed += element.changed;
+            counts.moved += element.moved;
+            output.report(&mismatch)?;
+            for line in render::text(&mismatch, &options) {
+                if args.max_diffs.is_some_and(|max| shown >= max) {
+                    hidden += 1;
+                } else {
+                    shown += 1;
+                    writeln!(output, "{line}")?;
+                }
+            }
+            Ok(())
+        },
+    )?;
+    if hidden > 0 {
+        writeln!(output, "... and {hidden} more")?;
+    }
+    writeln!(output, "{counts}")?;
+    Ok(!args.fails(counts))
+}
+
+fn check_three_way(output: &mut Output, result: ThreeWayMismatch) -> Result<bool, Error> {
+    for change in &result.changes {
+        writeln!(output, "{change}")?;
+    }
+    Ok(!result.has_conflicts())
 }
 
-pub fn check_diffs(result: Mismatch) -> Result<bool, Error> {
-    let mismatches = result.all_diffs();
-    let is_good = mismatches.is_empty();
-    for (d_type, key) in mismatches {
-        println!("{d_type}: {key}");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use json_diff::ds::key_node::KeyNode;
+
+    #[test]
+    fn cli_is_consistent() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn stream_rejects_unsupported_options() {
+        let parse = |args: &[&str]| Args::try_parse_from(args).unwrap().unsupported_by_stream();
+        assert_eq!(parse(&["json_diff", "file", "--stream", "a", "b"]), None);
+        assert_eq!(
+            parse(&[
+                "json_diff",
+                "--format",
+                "jsonl",
+                "file",
+                "--stream",
+                "a",
+                "b"
+            ]),
+            Some("--format")
+        );
+        assert_eq!(
+            parse(&["json_diff", "--at", "/a", "file", "--stream", "a", "b"]),
+            Some("--at")
+        );
+        assert_eq!(
+            parse(&["json_diff", "--format", "jsonl", "file", "a", "b"]),
+            None
+        );
+    }
+
+    #[test]
+    fn errors_map_to_exit_codes() {
+        let parse_error = serde_json::from_str::<Value>("{").unwrap_err();
+        assert_eq!(exit_code(&Error::JSON(parse_error)), EXIT_INPUT_ERROR);
+        assert_eq!(
+            exit_code(&Error::PointerNotFound("/a".to_string(), "source1")),
+            EXIT_INPUT_ERROR
+        );
+    }
+
+    #[test]
+    fn multi_compares_the_files_after_a_failing_one() {
+        let dir = std::env::temp_dir().join(format!("json_diff_multi_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str, content: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            path.display().to_string()
+        };
+        let (base, empty, other) = (
+            file("base.json", r#"{"a":1}"#),
+            file("empty.json", ""),
+            file("other.json", r#"{"a":2}"#),
+        );
+        let report = dir.join("report.jsonl").display().to_string();
+        let args = Args::try_parse_from([
+            "json_diff",
+            "--quiet",
+            "--report-json",
+            &report,
+            "multi",
+            &base,
+            &empty,
+            &other,
+        ])
+        .unwrap();
+        assert_eq!(run(&args).unwrap(), EXIT_INPUT_ERROR);
+        let lines = std::fs::read_to_string(&report).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(lines.contains(r#""right_file":"#), "{lines}");
+        assert!(lines.contains("other.json"), "{lines}");
+    }
+
+    #[test]
+    fn scores_leave_out_filtered_paths() {
+        let (json_1, json_2) = (r#"{"a":1,"b":2,"c":3}"#, r#"{"a":1,"b":3,"c":3}"#);
+        let score = |flags: &[&str]| {
+            let args = [
+                &["json_diff", "--format", "score"],
+                flags,
+                &["direct", "", ""],
+            ]
+            .concat();
+            let args = Args::try_parse_from(args).unwrap();
+            args.compare(json_1, json_2).unwrap().similarity()
+        };
+        assert_eq!(score(&[]), 2.0 / 3.0);
+        assert_eq!(score(&["--ignore-path", "b"]), 1.0);
+        assert_eq!(score(&["--only-path", "b"]), 0.0);
+        assert_eq!(score(&["--only-path", "c"]), 1.0);
+    }
+
+    /// A sink that fails every write, like a closed pipe
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_failures_are_not_input_errors() {
+        let read_error = Error::Decode("a.json".into(), std::io::ErrorKind::InvalidData.into());
+        assert_eq!(exit_code(&read_error), EXIT_INPUT_ERROR);
+
+        let mut output = Output::new(Box::new(Broken), None);
+        let err = writeln!(output, "report").unwrap_err();
+        assert_eq!(exit_code(&err), EXIT_INTERNAL_ERROR);
+
+        let mut output = Output::new(Box::new(std::io::sink()), Some(Box::new(Broken)));
+        let changed = KeyNode::Value(Value::from(1), Value::from(2));
+        let err = output
+            .report(&Mismatch::new(KeyNode::Nil, KeyNode::Nil, changed))
+            .unwrap_err();
+        assert_eq!(exit_code(&err), EXIT_INTERNAL_ERROR);
+
+        let missing_dir = std::env::temp_dir().join("json_diff_missing_dir/report.txt");
+        for flag in ["--output", "--report-json"] {
+            let path = missing_dir.to_str().unwrap();
+            let args = Args::try_parse_from(["json_diff", flag, path, "direct", "1", "1"]).unwrap();
+            let err = run(&args).unwrap_err();
+            assert!(matches!(err, Error::OutputFile(_)), "{err}");
+            assert_eq!(exit_code(&err), EXIT_INTERNAL_ERROR);
+        }
     }
-    Ok(is_good)
 }
@@ -0,0 +1,332 @@
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::path_glob::PathGlob;
+
+/// Numbers at paths matching `path` are equal if they differ by at most `epsilon`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tolerance {
+    pub path: PathGlob,
+    pub epsilon: f64,
+}
+
+/// How the epsilon of a `Tolerance` is applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToleranceMode {
+    /// `|a - b| <= epsilon`
+    #[default]
+    Absolute,
+    /// `|a - b| <= epsilon * max(|a|, |b|)`
+    Relative,
+}
+
+impl ToleranceMode {
+    /// True if `a` and `b` differ by at most `epsilon` in this mode
+    pub fn within(self, a: f64, b: f64, epsilon: f64) -> bool {
+        let allowed = match self {
+            ToleranceMode::Absolute => epsilon,
+            ToleranceMode::Relative => epsilon * a.abs().max(b.abs()),
+        };
+        (a - b).abs() <= allowed
+    }
+}
+
+impl FromStr for ToleranceMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "absolute" => Ok(ToleranceMode::Absolute),
+            "relative" => Ok(ToleranceMode::Relative),
+            _ => Err(format!(
+                "unknown tolerance mode '{s}', expected absolute or relative"
+            )),
+        }
+    }
+}
+
+/// How the elements of two arrays are aligned before they are compared
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayAlgorithm {
+    /// Myers diff, finds a shortest edit script quickly
+    #[default]
+    Myers,
+    /// Element `i` is compared with element `i`, the excess of the longer array is added or
+    /// removed
+    Positional,
+    /// Longest common subsequence by dynamic programming, takes time and memory proportional
+    /// to the product of both lengths
+    Lcs,
+}
+
+impl FromStr for ArrayAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "myers" => Ok(ArrayAlgorithm::Myers),
+            "positional" => Ok(ArrayAlgorithm::Positional),
+            "lcs" => Ok(ArrayAlgorithm::Lcs),
+            _ => Err(format!(
+                "unknown array algorithm '{s}', expected myers, positional or lcs"
+            )),
+        }
+    }
+}
+
+/// Unicode normalization form strings are brought into before they are compared
+#[cfg(feature = "unicode")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeForm {
+    /// Canonical composition, `e` followed by a combining accent becomes `é`
+    Nfc,
+    /// Canonical decomposition, `é` becomes `e` followed by a combining accent
+    Nfd,
+}
+
+#[cfg(feature = "unicode")]
+impl UnicodeForm {
+    pub fn normalize(self, s: &str) -> String {
+        use unicode_normalization::UnicodeNormalization;
+        match self {
+            UnicodeForm::Nfc => s.nfc().collect(),
+            UnicodeForm::Nfd => s.nfd().collect(),
+        }
+    }
+}
+
+#[cfg(feature = "unicode")]
+impl FromStr for UnicodeForm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nfc" => Ok(UnicodeForm::Nfc),
+            "nfd" => Ok(UnicodeForm::Nfd),
+            _ => Err(format!(
+                "unknown normalization form '{s}', expected nfc or nfd"
+            )),
+        }
+    }
+}
+
+/// Parses `<path glob>=<epsilon>`, e.g. `**.price=0.01`
+impl FromStr for Tolerance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, epsilon) = s
+            .rsplit_once('=')
+            .ok_or_else(|| format!("expected path=epsilon, got '{s}'"))?;
+        let epsilon: f64 = epsilon
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid epsilon '{}'", epsilon.trim()))?;
+        if !epsilon.is_finite() || epsilon < 0.0 {
+            return Err(format!(
+                "epsilon must be a non-negative number, got {epsilon}"
+            ));
+        }
+        Ok(Self {
+            path: path.trim().parse()?,
+            epsilon,
+        })
+    }
+}
+
+/// Settings of a comparison. `DiffOptions::default()` compares arrays in order, reports every
+/// key and every changed value; the setters can be chained to change single settings:
+///
+/// ```
+/// use json_diff::options::DiffOptions;
+/// let options = DiffOptions::default().sort_arrays(true).null_equals_missing(true);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Deep-sort all arrays before comparing them. Default: `false`
+    pub sort_arrays: bool,
+    /// Sort only arrays whose elements are all scalars, other arrays keep their order.
+    /// Default: `false`
+    pub sort_scalar_arrays: bool,
+    /// Sort arrays whose elements are all objects by the canonical json of each object before
+    /// aligning them, so reordered records are no difference and duplicates match by their
+    /// multiplicity. Default: `false`
+    pub sort_object_arrays: bool,
+    /// Drop array elements equal to an earlier element of the same array before aligning,
+    /// for arrays that are sets but keep their order. Indices in the mismatch are those of the
+    /// kept elements in the original arrays. Default: `false`
+    pub array_dedup: bool,
+    /// Skip object keys whose name matches one of these, together with their subtree.
+    /// Default: empty
+    pub ignore_keys: Vec<Regex>,
+    /// Object keys that only differ in case are compared with each other, e.g. `UserName` and
+    /// `username`. Keys with an exact match are paired first, the others pair up in document
+    /// order, keys left over are reported as added or removed. Default: `false`
+    pub ignore_key_case: bool,
+    /// A key that is `null` on one side and absent on the other is no difference.
+    /// Default: `false`
+    pub null_equals_missing: bool,
+    /// Only compare the structure: scalars only differ if their type changed and arrays are
+    /// compared index by index. Default: `false`
+    pub keys_only: bool,
+    /// Check that the first value is contained in the second: object keys only present in the
+    /// second value are no difference. Default: `false`
+    pub subset: bool,
+    /// Two different strings that both hold a json document are compared as parsed json.
+    /// Default: `false`
+    pub parse_embedded_json: bool,
+    /// Parts of string values matching one of these are replaced by a placeholder on both
+    /// sides before comparing, e.g. timestamps or uuids. Default: empty
+    pub masks: Vec<Regex>,
+    /// `{}`, `[]` and `null` are equal to each other, other values like `0` or `""` are not.
+    /// Default: `false`
+    pub empty_equals_null: bool,
+    /// A string equals a number or bool it spells, e.g. `"1"` and `1` or `"true"` and `true`.
+    /// Default: `false`
+    pub coerce_scalars: bool,
+    /// Numbers are equal if they denote the same value, whatever their notation, e.g. `1.0`,
+    /// `1e0` and `1`. Compared exactly, without the rounding of `f64`: big integers only equal
+    /// a float that holds exactly their value. Default: `false`
+    pub numeric_exact: bool,
+    /// Numeric tolerances per path. If several rules match a path the last one wins, numbers
+    /// at paths without a rule must be equal. Default: empty
+    pub tolerances: Vec<Tolerance>,
+    /// Whether the epsilon of `tolerances` is absolute or relative to the larger number.
+    /// Default: `ToleranceMode::Absolute`
+    pub tolerance_mode: ToleranceMode,
+    /// How array elements are aligned, ignored with `keys_only` which always compares by
+    /// position. Default: `ArrayAlgorithm::Myers`
+    pub array_algorithm: ArrayAlgorithm,
+    /// Arrays longer than this are compared as multisets instead of being aligned: equal
+    /// elements match wherever they are, the rest is reported as removed or added and never
+    /// compared pairwise. Default: `None`, no limit
+    pub array_threshold: Option<usize>,
+    /// An array element removed at one index and added at another with an equal value is
+    /// reported as moved instead of as removal and addition. Default: `false`
+    pub detect_moves: bool,
+    /// Stop at the first difference found: the mismatch holds that single difference, which is
+    /// enough to tell whether the values are equal. Which difference is found first is not
+    /// specified, `equal_leaves` is not counted. Default: `false`
+    pub stop_at_first: bool,
+    /// Count the leaves that are equal on both sides into `Mismatch::equal_leaves`, see
+    /// `process::unchanged_leaves` for what counts as a leaf. Default: `false`
+    pub count_equal: bool,
+    /// Strings are normalized to this form before they are compared, so canonically
+    /// equivalent strings are equal. Default: `None`
+    #[cfg(feature = "unicode")]
+    pub unicode_normalize: Option<UnicodeForm>,
+}
+
+impl DiffOptions {
+    pub fn sort_arrays(mut self, sort_arrays: bool) -> Self {
+        self.sort_arrays = sort_arrays;
+        self
+    }
+
+    pub fn sort_scalar_arrays(mut self, sort_scalar_arrays: bool) -> Self {
+        self.sort_scalar_arrays = sort_scalar_arrays;
+        self
+    }
+
+    pub fn sort_object_arrays(mut self, sort_object_arrays: bool) -> Self {
+        self.sort_object_arrays = sort_object_arrays;
+        self
+    }
+
+    pub fn array_dedup(mut self, array_dedup: bool) -> Self {
+        self.array_dedup = array_dedup;
+        self
+    }
+
+    pub fn ignore_keys(mut self, ignore_keys: Vec<Regex>) -> Self {
+        self.ignore_keys = ignore_keys;
+        self
+    }
+
+    pub fn ignore_key_case(mut self, ignore_key_case: bool) -> Self {
+        self.ignore_key_case = ignore_key_case;
+        self
+    }
+
+    pub fn null_equals_missing(mut self, null_equals_missing: bool) -> Self {
+        self.null_equals_missing = null_equals_missing;
+        self
+    }
+
+    pub fn keys_only(mut self, keys_only: bool) -> Self {
+        self.keys_only = keys_only;
+        self
+    }
+
+    pub fn subset(mut self, subset: bool) -> Self {
+        self.subset = subset;
+        self
+    }
+
+    pub fn parse_embedded_json(mut self, parse_embedded_json: bool) -> Self {
+        self.parse_embedded_json = parse_embedded_json;
+        self
+    }
+
+    pub fn masks(mut self, masks: Vec<Regex>) -> Self {
+        self.masks = masks;
+        self
+    }
+
+    pub fn empty_equals_null(mut self, empty_equals_null: bool) -> Self {
+        self.empty_equals_null = empty_equals_null;
+        self
+    }
+
+    pub fn coerce_scalars(mut self, coerce_scalars: bool) -> Self {
+        self.coerce_scalars = coerce_scalars;
+        self
+    }
+
+    pub fn numeric_exact(mut self, numeric_exact: bool) -> Self {
+        self.numeric_exact = numeric_exact;
+        self
+    }
+
+    pub fn tolerances(mut self, tolerances: Vec<Tolerance>) -> Self {
+        self.tolerances = tolerances;
+        self
+    }
+
+    pub fn tolerance_mode(mut self, tolerance_mode: ToleranceMode) -> Self {
+        self.tolerance_mode = tolerance_mode;
+        self
+    }
+
+    pub fn array_algorithm(mut self, array_algorithm: ArrayAlgorithm) -> Self {
+        self.array_algorithm = array_algorithm;
+        self
+    }
+
+    pub fn array_threshold(mut self, array_threshold: Option<usize>) -> Self {
+        self.array_threshold = array_threshold;
+        self
+    }
+
+    pub fn detect_moves(mut self, detect_moves: bool) -> Self {
+        self.detect_moves = detect_moves;
+        self
+    }
+
+    pub fn stop_at_first(mut self, stop_at_first: bool) -> Self {
+        self.stop_at_first = stop_at_first;
+        self
+    }
+
+    pub fn count_equal(mut self, count_equal: bool) -> Self {
+        self.count_equal = count_equal;
+        self
+    }
+
+    #[cfg(feature = "unicode")]
+    pub fn unicode_normalize(mut self, unicode_normalize: Option<UnicodeForm>) -> Self {
+        self.unicode_normalize = unicode_normalize;
+        self
+    }
+}
@@ -0,0 +1,422 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Formatter;
+
+use serde::de::{DeserializeSeed, Error as _, MapAccess, SeqAccess, Visitor};
+use serde::Deserializer;
+use serde_json::{Map, Number, Value};
+
+use crate::ds::key_node::{
+    array_label_index, array_label_right_index, join_path, split_path, PATH_SEPARATOR,
+};
+use crate::enums::Error;
+
+/// Key under which serde_json passes the text of a number with `arbitrary_precision`
+#[cfg(feature = "arbitrary_precision")]
+const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Parses a json document, rejecting objects that contain the same key twice.
+/// `serde_json` would silently keep the last value instead.
+pub fn parse_json_strict(s: &str) -> Result<Value, Error> {
+    let state = ParseState::default();
+    let mut deserializer = serde_json::Deserializer::from_str(s);
+    match (StrictValue { state: &state }).deserialize(&mut deserializer) {
+        Ok(value) => {
+            deserializer.end().map_err(Error::JSON)?;
+            Ok(value)
+        }
+        Err(err) => Err(state
+            .duplicate
+            .take()
+            .map(Error::DuplicateKey)
+            .unwrap_or(Error::JSON(err))),
+    }
+}
+
+/// Parses a json document. Syntax errors are `Error::JSON`, which the caller attributes to the
+/// document with `Error::in_source`.
+pub fn parse_json(s: &str, strict_duplicate_keys: bool) -> Result<Value, Error> {
+    if strict_duplicate_keys {
+        parse_json_strict(s)
+    } else {
+        serde_json::from_str(s).map_err(Error::JSON)
+    }
+}
+
+/// Parses a JSON5 document, which allows comments, trailing commas, unquoted keys and more,
+/// into the same `Value` a strict json document would produce.
+pub fn parse_json5(s: &str, strict_duplicate_keys: bool) -> Result<Value, Error> {
+    if !strict_duplicate_keys {
+        return json5::from_str(s).map_err(Error::Json5);
+    }
+    let state = ParseState::default();
+    let mut deserializer = json5::Deserializer::from_str(s).map_err(Error::Json5)?;
+    (StrictValue { state: &state })
+        .deserialize(&mut deserializer)
+        .map_err(|err| {
+            state
+                .duplicate
+                .take()
+                .map(Error::DuplicateKey)
+                .unwrap_or(Error::Json5(err))
+        })
+}
+
+/// Line and column (both starting at 1) of every object key and array element in a json text,
+/// indexed by the `->` joined path the diff uses. Built by a lenient scan, so a malformed
+/// document yields the positions up to the first error.
+#[derive(Debug, Default)]
+pub struct SourcePositions {
+    positions: HashMap<String, (usize, usize)>,
+}
+
+impl SourcePositions {
+    pub fn of(s: &str) -> Self {
+        let mut scanner = Scanner {
+            chars: s.chars().peekable(),
+            line: 1,
+            column: 1,
+            positions: HashMap::new(),
+        };
+        scanner.value(&mut Vec::new());
+        Self {
+            positions: scanner.positions,
+        }
+    }
+
+    /// Position of the entry at `path`; a trailing `->` and the `-<value>` suffix of one-sided
+    /// array entries are ignored
+    pub fn get(&self, path: &str) -> Option<(usize, usize)> {
+        self.get_with(path, PATH_SEPARATOR)
+    }
+
+    /// Like `get`, for a path whose segments are joined with `separator`. Array labels that
+    /// name a left and a right index are looked up by the left one.
+    pub fn get_with(&self, path: &str, separator: &str) -> Option<(usize, usize)> {
+        self.lookup(path, separator, array_label_index)
+    }
+
+    /// Like `get_with`, for a path into the right document: array labels that name a left and
+    /// a right index are looked up by the right one
+    pub fn get_right_with(&self, path: &str, separator: &str) -> Option<(usize, usize)> {
+        self.lookup(path, separator, array_label_right_index)
+    }
+
+    fn lookup(
+        &self,
+        path: &str,
+        separator: &str,
+        index: fn(&str) -> Option<usize>,
+    ) -> Option<(usize, usize)> {
+        let path = path.strip_suffix(separator).unwrap_or(path);
+        let segments: Vec<String> = split_path(path, separator)
+            .into_iter()
+            .map(|segment| match index(&segment) {
+                Some(index) => format!("[l: {index}]"),
+                None => segment,
+            })
+            .collect();
+        self.positions.get(&join_path(&segments)).copied()
+    }
+}
+
+struct Scanner<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+    positions: HashMap<String, (usize, usize)>,
+}
+
+impl<'a> Scanner<'a> {
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) -> Option<char> {
+        while self.chars.peek()?.is_whitespace() {
+            self.bump();
+        }
+        self.chars.peek().copied()
+    }
+
+    fn record(&mut self, path: &[String]) {
+        let position = (self.line, self.column);
+        self.positions.entry(join_path(path)).or_insert(position);
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let mut raw = String::from('"');
+        self.bump();
+        loop {
+            let c = self.bump()?;
+            raw.push(c);
+            match c {
+                '\\' => raw.push(self.bump()?),
+                '"' => return serde_json::from_str(&raw).ok(),
+                _ => {}
+            }
+        }
+    }
+
+    fn value(&mut self, path: &mut Vec<String>) -> Option<()> {
+        let next = self.skip_whitespace()?;
+        self.record(path);
+        match next {
+            '{' => {
+                self.bump();
+                loop {
+                    match self.skip_whitespace()? {
+                        '}' => break,
+                        ',' => {
+                            self.bump();
+                        }
+                        '"' => {
+                            let position = (self.line, self.column);
+                            let key = self.string()?;
+                            path.push(key);
+                            self.positions.entry(join_path(path)).or_insert(position);
+                            if self.skip_whitespace()? != ':' {
+                                return None;
+                            }
+                            self.bump();
+                            self.value(path)?;
+                            path.pop();
+                        }
+                        _ => return None,
+                    }
+                }
+                self.bump();
+            }
+            '[' => {
+                self.bump();
+                let mut index = 0;
+                loop {
+                    match self.skip_whitespace()? {
+                        ']' => break,
+                        ',' => {
+                            self.bump();
+                        }
+                        _ => {
+                            path.push(format!("[l: {index}]"));
+                            self.value(path)?;
+                            path.pop();
+                            index += 1;
+                        }
+                    }
+                }
+                self.bump();
+            }
+            '"' => {
+                self.string()?;
+            }
+            _ => {
+                while !matches!(self.chars.peek(), None | Some(',' | '}' | ']'))
+                    && !self.chars.peek()?.is_whitespace()
+                {
+                    self.bump();
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+#[derive(Default)]
+struct ParseState {
+    path: RefCell<Vec<String>>,
+    duplicate: RefCell<Option<String>>,
+}
+
+impl ParseState {
+    fn nested<T, E>(&self, segment: String, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        self.path.borrow_mut().push(segment);
+        let result = f();
+        self.path.borrow_mut().pop();
+        result
+    }
+}
+
+#[derive(Clone, Copy)]
+struct StrictValue<'a> {
+    state: &'a ParseState,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for StrictValue<'a> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for StrictValue<'a> {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("any valid json value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(v).map_or(Value::Null, Value::Number))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::new();
+        loop {
+            let segment = format!("[l: {}]", values.len());
+            match self.state.nested(segment, || seq.next_element_seed(self))? {
+                Some(value) => values.push(value),
+                None => return Ok(Value::Array(values)),
+            }
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut values = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            #[cfg(feature = "arbitrary_precision")]
+            if values.is_empty() && key == NUMBER_TOKEN {
+                // serde_json hands over numbers as a map with a single private key in this mode
+                let number: String = map.next_value()?;
+                return number.parse().map(Value::Number).map_err(A::Error::custom);
+            }
+            if values.contains_key(&key) {
+                let mut path = self.state.path.borrow().clone();
+                path.push(key);
+                let path = join_path(&path);
+                let err = A::Error::custom(format!("duplicate key at {path}"));
+                *self.state.duplicate.borrow_mut() = Some(path);
+                return Err(err);
+            }
+            let value = self
+                .state
+                .nested(key.clone(), || map.next_value_seed(self))?;
+            values.insert(key, value);
+        }
+        Ok(Value::Object(values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strict_parse_matches_serde_json() {
+        let data = r#"{"a":[1,2.5,{"b":null}],"c":{"d":"e","f":true},"g":-3}"#;
+        let strict = parse_json_strict(data).unwrap();
+        let lenient: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(strict, lenient);
+    }
+
+    #[test]
+    fn duplicate_key_at_root() {
+        let result = parse_json_strict(r#"{"a":1,"a":2}"#);
+        assert!(matches!(result, Err(Error::DuplicateKey(path)) if path == "a"));
+    }
+
+    #[test]
+    fn duplicate_key_nested() {
+        let result = parse_json_strict(r#"{"a":[{"b":1},{"c":1,"c":2}]}"#);
+        assert!(matches!(result, Err(Error::DuplicateKey(path)) if path == "a->[l: 1]->c"));
+    }
+
+    #[test]
+    fn strict_parse_reports_syntax_errors() {
+        assert!(matches!(parse_json_strict("{invalid"), Err(Error::JSON(_))));
+        assert!(matches!(parse_json_strict("{} {}"), Err(Error::JSON(_))));
+    }
+
+    #[test]
+    fn json5_matches_json() {
+        let data5 = r#"{
+            // comment
+            a: [1, 2.5, {b: null,},],
+            'c': {"d": 'e', f: true},
+            /* block */ g: -3,
+        }"#;
+        let data = r#"{"a":[1,2.5,{"b":null}],"c":{"d":"e","f":true},"g":-3}"#;
+        let expected: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(parse_json5(data5, false).unwrap(), expected);
+        assert_eq!(parse_json5(data5, true).unwrap(), expected);
+        assert_eq!(parse_json5(data, false).unwrap(), expected);
+    }
+
+    #[test]
+    fn json5_errors() {
+        assert!(matches!(parse_json5("{a:", false), Err(Error::Json5(_))));
+        let result = parse_json5("{a: {b: 1, b: 2}}", true);
+        assert!(matches!(result, Err(Error::DuplicateKey(path)) if path == "a->b"));
+    }
+
+    #[test]
+    fn source_positions() {
+        let data = "{\n  \"a\": 1,\n  \"b\": [\n    \"x\",\n    {\"c\": null}\n  ],\n  \"d\\\"e\": true\n}";
+        let positions = SourcePositions::of(data);
+        assert_eq!(positions.get(""), Some((1, 1)));
+        assert_eq!(positions.get("a->"), Some((2, 3)));
+        assert_eq!(positions.get("b"), Some((3, 3)));
+        assert_eq!(positions.get("b->[l: 0]"), Some((4, 5)));
+        assert_eq!(positions.get("b->[l: 0]-\"x\""), Some((4, 5)));
+        assert_eq!(positions.get("b->[l: 1]->c"), Some((5, 6)));
+        assert_eq!(positions.get("d\"e"), Some((7, 3)));
+        assert_eq!(positions.get("nope"), None);
+        assert_eq!(positions.get("b->[l: 1 r: 0]->c"), Some((5, 6)));
+        assert_eq!(
+            positions.get_right_with("b->[l: 0 r: 1]->c", "->"),
+            Some((5, 6))
+        );
+    }
+
+    #[test]
+    fn source_positions_of_keys_with_separator() {
+        let positions = SourcePositions::of("{\"a->b\": {\"c\": [1]}}");
+        assert_eq!(positions.get("\"a->b\"->c"), Some((1, 11)));
+        assert_eq!(positions.get("\"a->b\"->c->[l: 0]-1"), Some((1, 17)));
+        assert_eq!(positions.get("a->b->c"), None);
+    }
+
+    #[test]
+    fn source_positions_of_broken_json() {
+        let positions = SourcePositions::of("{\"a\": 1,\n \"b\": ");
+        assert_eq!(positions.get("a"), Some((1, 2)));
+        assert_eq!(positions.get("b"), Some((2, 2)));
+    }
+
+    #[test]
+    fn duplicates_allowed_when_not_strict() {
+        let value = parse_json(r#"{"a":1,"a":2}"#, false).unwrap();
+        assert_eq!(value, serde_json::json!({"a": 2}));
+    }
+}
@@ -0,0 +1,411 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::ds::key_node::{array_label_index, array_label_right_index, json_pointer};
+use crate::ds::mismatch::DiffEntry;
+use crate::enums::{ChangeKind, Error, Which};
+
+/// A single RFC 6902 operation, `move`, `copy` and `test` are not supported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+}
+
+pub fn parse_patch(s: &str) -> Result<Vec<PatchOperation>, Error> {
+    serde_json::from_str(s).map_err(|err| Error::Json {
+        which: Which::Patch,
+        err,
+    })
+}
+
+/// Applies the operations in order. If one of them cannot be applied, `base` is left as it was.
+pub fn apply_patch(base: &mut Value, ops: &[PatchOperation]) -> Result<(), Error> {
+    let mut patched = base.clone();
+    for op in ops {
+        match op {
+            PatchOperation::Add { path, value } => add(&mut patched, path, value.clone())?,
+            PatchOperation::Remove { path } => {
+                remove(&mut patched, path)?;
+            }
+            PatchOperation::Replace { path, value } => {
+                *patched
+                    .pointer_mut(path)
+                    .ok_or_else(|| patch_error("path does not exist", path))? = value.clone();
+            }
+        }
+    }
+    *base = patched;
+    Ok(())
+}
+
+/// A reference token of a JSON pointer, array indices compare as numbers
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Token {
+    Index(usize),
+    Key(String),
+}
+
+/// The tokens of a diff path, array labels become their left or their right index
+fn tokens(path: &[String], right: bool) -> Vec<Token> {
+    path.iter()
+        .map(|segment| {
+            let index = if right {
+                array_label_right_index(segment)
+            } else {
+                array_label_index(segment)
+            };
+            index.map_or_else(|| Token::Key(segment.clone()), Token::Index)
+        })
+        .collect()
+}
+
+fn pointer(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Index(index) => format!("/{index}"),
+            Token::Key(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+        })
+        .collect()
+}
+
+/// Builds the RFC 6902 patch that turns the left document of `entries` into `right`, the
+/// right document they were found in. Changed values are replaced first, then removed and
+/// moved elements are removed from the back and added and moved ones are added from the
+/// front, so that every pointer is valid when its operation is applied.
+///
+/// The patch reproduces `right` exactly if the entries are all differences found with the
+/// default options, options that hide differences leave them out of the patch as well.
+pub fn to_json_patch(entries: &[DiffEntry], right: &Value) -> Result<Vec<PatchOperation>, Error> {
+    let mut replaced = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for entry in entries {
+        match entry.kind {
+            ChangeKind::Changed => replaced.push((tokens(&entry.path, false), entry.right.clone())),
+            ChangeKind::Removed => removed.push(tokens(&entry.path, false)),
+            ChangeKind::Added => added.push((tokens(&entry.path, true), entry.right.clone())),
+            ChangeKind::Moved => {
+                removed.push(tokens(&entry.path, false));
+                let parent = entry
+                    .path
+                    .split_last()
+                    .map_or(&[][..], |(_, parent)| parent);
+                let mut to = tokens(parent, true);
+                to.extend(entry.moved_to.map(Token::Index));
+                added.push((to, entry.right.clone()));
+            }
+        }
+    }
+    removed.sort_unstable_by(|a, b| b.cmp(a));
+    added.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    let value_at = |tokens: &[Token], value: Option<Value>| {
+        let path = pointer(tokens);
+        match value.or_else(|| right.pointer(&path).cloned()) {
+            Some(value) => Ok((path, value)),
+            None => Err(patch_error("no value in the second document", &path)),
+        }
+    };
+    let mut ops = Vec::new();
+    for (tokens, value) in replaced {
+        let (path, value) = value_at(&tokens, value)?;
+        ops.push(PatchOperation::Replace { path, value });
+    }
+    ops.extend(removed.iter().map(|tokens| PatchOperation::Remove {
+        path: pointer(tokens),
+    }));
+    for (tokens, value) in added {
+        let (path, value) = value_at(&tokens, value)?;
+        ops.push(PatchOperation::Add { path, value });
+    }
+    Ok(ops)
+}
+
+/// An RFC 7386 merge patch, see `to_merge_patch`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergePatch {
+    pub patch: Value,
+    /// Pointers of keys the patch sets to `null`. A merge patch removes every key it sets to
+    /// `null`, so applying it drops these keys instead of keeping them as `null`.
+    pub ambiguous_nulls: Vec<String>,
+}
+
+/// Builds the RFC 7386 merge patch that turns `left` into `right`: removed keys become `null`,
+/// objects are patched key by key and all other changed values, including arrays, are replaced
+/// as a whole. Equal documents give the empty patch `{}`.
+///
+/// Keys whose value is `null` in `right` can't be expressed in a merge patch, they are written
+/// as `null` anyway and listed in `MergePatch::ambiguous_nulls`.
+pub fn to_merge_patch(left: &Value, right: &Value) -> MergePatch {
+    let mut ambiguous_nulls = Vec::new();
+    let patch = merge_patch(left, right, &mut Vec::new(), &mut ambiguous_nulls)
+        .unwrap_or_else(|| Value::Object(Map::new()));
+    MergePatch {
+        patch,
+        ambiguous_nulls,
+    }
+}
+
+/// The patch of `right` against `left`, `None` if both are equal
+fn merge_patch(
+    left: &Value,
+    right: &Value,
+    path: &mut Vec<String>,
+    ambiguous_nulls: &mut Vec<String>,
+) -> Option<Value> {
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => {
+            let mut patch: Map<String, Value> = left
+                .keys()
+                .filter(|key| !right.contains_key(*key))
+                .map(|key| (key.clone(), Value::Null))
+                .collect();
+            for (key, value) in right {
+                path.push(key.clone());
+                let changed = match left.get(key) {
+                    Some(old) => merge_patch(old, value, path, ambiguous_nulls),
+                    None => Some(replacement(value, path, ambiguous_nulls)),
+                };
+                path.pop();
+                if let Some(changed) = changed {
+                    patch.insert(key.clone(), changed);
+                }
+            }
+            (!patch.is_empty()).then_some(Value::Object(patch))
+        }
+        _ if left == right => None,
+        _ => Some(replacement(right, path, ambiguous_nulls)),
+    }
+}
+
+/// A value that replaces whatever was at `path`. Objects in it are merged into nothing when the
+/// patch is applied, so their `null` values are lost as well. Arrays are taken literally.
+fn replacement(value: &Value, path: &mut Vec<String>, ambiguous_nulls: &mut Vec<String>) -> Value {
+    match value {
+        Value::Null if !path.is_empty() => ambiguous_nulls.push(json_pointer(path)),
+        Value::Object(map) => {
+            for (key, value) in map {
+                path.push(key.clone());
+                replacement(value, path, ambiguous_nulls);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+    value.clone()
+}
+
+fn patch_error(msg: &str, path: &str) -> Error {
+    Error::Patch(format!("{msg}: '{path}'"))
+}
+
+/// Splits a pointer into the pointer of its parent and the unescaped last token
+fn split_pointer(path: &str) -> Result<(&str, String), Error> {
+    let idx = path
+        .rfind('/')
+        .ok_or_else(|| patch_error("invalid pointer", path))?;
+    let token = path[idx + 1..].replace("~1", "/").replace("~0", "~");
+    Ok((&path[..idx], token))
+}
+
+fn array_index(token: &str, len: usize, path: &str) -> Result<usize, Error> {
+    match token.parse::<usize>() {
+        Ok(idx) if idx <= len && (token == "0" || !token.starts_with('0')) => Ok(idx),
+        _ => Err(patch_error("invalid array index", path)),
+    }
+}
+
+fn add(base: &mut Value, path: &str, value: Value) -> Result<(), Error> {
+    if path.is_empty() {
+        *base = value;
+        return Ok(());
+    }
+    let (parent, token) = split_pointer(path)?;
+    match base.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(token, value);
+        }
+        Some(Value::Array(vec)) => {
+            let idx = if token == "-" {
+                vec.len()
+            } else {
+                array_index(&token, vec.len(), path)?
+            };
+            vec.insert(idx, value);
+        }
+        _ => return Err(patch_error("parent does not exist", path)),
+    }
+    Ok(())
+}
+
+fn remove(base: &mut Value, path: &str) -> Result<Value, Error> {
+    let (parent, token) = split_pointer(path)?;
+    let removed = match base.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&token),
+        Some(Value::Array(vec)) => match array_index(&token, vec.len(), path) {
+            Ok(idx) if idx < vec.len() => Some(vec.remove(idx)),
+            _ => None,
+        },
+        _ => None,
+    };
+    removed.ok_or_else(|| patch_error("path does not exist", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::{ArrayAlgorithm, DiffOptions};
+    use crate::process::match_json_with_options;
+    use serde_json::json;
+
+    #[test]
+    fn merge_patch_of_changes() {
+        let left = json!({"a": 1, "b": {"c": 1, "d": 2}, "e": [1, 2], "f": "x"});
+        let right = json!({"a": 1, "b": {"c": 3}, "e": [1, null], "g": {"h": true}});
+        let merge = to_merge_patch(&left, &right);
+        assert_eq!(
+            merge.patch,
+            json!({"b": {"c": 3, "d": null}, "e": [1, null], "f": null, "g": {"h": true}})
+        );
+        assert!(merge.ambiguous_nulls.is_empty());
+        assert_eq!(to_merge_patch(&left, &left).patch, json!({}));
+        assert_eq!(to_merge_patch(&left, &json!(null)).patch, json!(null));
+    }
+
+    #[test]
+    fn merge_patch_reports_null_values() {
+        let left = json!({"a": 1, "b": null, "c": 2});
+        let right = json!({"a": null, "b": null, "d": null, "e": {"f": null, "g": [null]}});
+        let merge = to_merge_patch(&left, &right);
+        assert_eq!(
+            merge.patch,
+            json!({"a": null, "c": null, "d": null, "e": {"f": null, "g": [null]}})
+        );
+        assert_eq!(merge.ambiguous_nulls, vec!["/a", "/d", "/e/f"]);
+    }
+
+    #[test]
+    fn apply_parsed_patch() {
+        let mut base = json!({"a": 1, "b": {"c": [1, 2, 3]}, "d": "x"});
+        let target = json!({"a": 2, "b": {"c": [1, 3, 4], "e~f/g": true}});
+        let ops = parse_patch(
+            r#"[
+                {"op": "replace", "path": "/a", "value": 2},
+                {"op": "remove", "path": "/b/c/1"},
+                {"op": "add", "path": "/b/c/-", "value": 4},
+                {"op": "add", "path": "/b/e~0f~1g", "value": true},
+                {"op": "remove", "path": "/d"}
+            ]"#,
+        )
+        .unwrap();
+        apply_patch(&mut base, &ops).unwrap();
+        assert_eq!(base, target);
+    }
+
+    #[test]
+    fn generated_patch_reproduces_target() {
+        let cases = [
+            (json!({"a": 1, "d": "x"}), json!({"a": 2, "e~f/g": true})),
+            (json!([1, 2, 3]), json!([0, 1, 3, 4, 5])),
+            (json!([1, 2, 3, 4, 5]), json!([2, 4])),
+            (
+                json!({"l": [{"k": 1}, [1, 2], "x", {"k": [3, 4]}]}),
+                json!({"l": ["y", {"k": 2}, [2, 3], {"k": [3]}, "z", {"new": null}]}),
+            ),
+            (json!([[1, 2], [3]]), json!([[0], [1, 2, 5], [3, 4], []])),
+            (json!({"a": [1]}), json!([1])),
+            (json!(1), json!(2)),
+        ];
+        for (left, right) in cases {
+            for options in [
+                DiffOptions::default(),
+                DiffOptions::default().detect_moves(true),
+                DiffOptions::default().array_algorithm(ArrayAlgorithm::Lcs),
+                DiffOptions::default().array_algorithm(ArrayAlgorithm::Positional),
+            ] {
+                let entries = match_json_with_options(&left, &right, &options).diffs();
+                let ops = to_json_patch(&entries, &right).unwrap();
+                let serialized = serde_json::to_string(&ops).unwrap();
+                let mut patched = left.clone();
+                apply_patch(&mut patched, &parse_patch(&serialized).unwrap()).unwrap();
+                assert_eq!(patched, right, "{left} -> {right}: {serialized}");
+            }
+        }
+    }
+
+    #[test]
+    fn generated_patch_of_moves() {
+        let left = json!(["a", "b", "c"]);
+        let right = json!(["c", "a", "b"]);
+        let options = DiffOptions::default().detect_moves(true);
+        let entries = match_json_with_options(&left, &right, &options).diffs();
+        assert_eq!(
+            to_json_patch(&entries, &right).unwrap(),
+            vec![
+                PatchOperation::Remove {
+                    path: "/2".to_string()
+                },
+                PatchOperation::Add {
+                    path: "/0".to_string(),
+                    value: json!("c")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_add_at_root_and_array_index() {
+        let mut base = json!([1, 3]);
+        let ops = vec![PatchOperation::Add {
+            path: "/1".to_string(),
+            value: json!(2),
+        }];
+        apply_patch(&mut base, &ops).unwrap();
+        assert_eq!(base, json!([1, 2, 3]));
+
+        let ops = vec![PatchOperation::Add {
+            path: "".to_string(),
+            value: json!({"new": "doc"}),
+        }];
+        apply_patch(&mut base, &ops).unwrap();
+        assert_eq!(base, json!({"new": "doc"}));
+    }
+
+    #[test]
+    fn apply_fails_on_missing_paths() {
+        let mut base = json!({"a": [1]});
+        for op in [
+            r#"[{"op": "remove", "path": "/b"}]"#,
+            r#"[{"op": "remove", "path": "/a/1"}]"#,
+            r#"[{"op": "replace", "path": "/a/5", "value": 1}]"#,
+            r#"[{"op": "add", "path": "/x/y", "value": 1}]"#,
+            r#"[{"op": "add", "path": "/a/01", "value": 1}]"#,
+        ] {
+            let ops = parse_patch(op).unwrap();
+            assert!(matches!(apply_patch(&mut base, &ops), Err(Error::Patch(_))));
+        }
+        assert_eq!(base, json!({"a": [1]}));
+
+        let ops = parse_patch(
+            r#"[{"op": "add", "path": "/a/-", "value": 2}, {"op": "remove", "path": "/b"}]"#,
+        )
+        .unwrap();
+        assert!(apply_patch(&mut base, &ops).is_err());
+        assert_eq!(base, json!({"a": [1]}));
+    }
+
+    #[test]
+    fn unsupported_operations_are_rejected() {
+        let result = parse_patch(r#"[{"op": "move", "from": "/a", "path": "/b"}]"#);
+        assert!(matches!(
+            result,
+            Err(Error::Json {
+                which: Which::Patch,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+use std::str::FromStr;
+
+use crate::ds::key_node::{array_label_index, array_label_right_index};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// `*`, any single key or array element
+    Any,
+    /// `**`, any number of segments including none
+    AnyDepth,
+    Literal(String),
+}
+
+/// A dotted path pattern like `items.*.updated_at` or `a.**.id`. `*` stands for one key or
+/// array element, `**` for any number of them. A number also matches the array element at
+/// that index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathGlob {
+    segments: Vec<Segment>,
+}
+
+impl FromStr for PathGlob {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("empty path pattern".to_string());
+        }
+        let segments = s
+            .split('.')
+            .map(|segment| match segment {
+                "" => Err(format!("empty segment in path pattern '{s}'")),
+                "*" => Ok(Segment::Any),
+                "**" => Ok(Segment::AnyDepth),
+                literal => Ok(Segment::Literal(literal.to_string())),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { segments })
+    }
+}
+
+/// Parses the patterns of an ignore file, one per line. Blank lines and lines starting with
+/// `#` are skipped, errors name the line they were found in.
+pub fn parse_ignore_rules(s: &str) -> Result<Vec<PathGlob>, String> {
+    s.lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| line.parse().map_err(|err| format!("line {number}: {err}")))
+        .collect()
+}
+
+impl PathGlob {
+    /// True if the pattern matches the whole path
+    pub fn matches<S: AsRef<str>>(&self, path: &[S]) -> bool {
+        matches(&self.segments, path)
+    }
+
+    /// True if the pattern matches the path or one of its ancestors, so everything below a
+    /// matched key is covered as well
+    pub fn covers<S: AsRef<str>>(&self, path: &[S]) -> bool {
+        (0..=path.len()).any(|len| self.matches(&path[..len]))
+    }
+
+    /// True if the pattern could match a path below this one, so a subtree at the path may
+    /// hold matches
+    pub fn leads_to<S: AsRef<str>>(&self, path: &[S]) -> bool {
+        leads_to(&self.segments, path)
+    }
+}
+
+/// True if one of `globs` covers the path, which drops it with `--ignore-path`
+pub fn ignores<S: AsRef<str>>(globs: &[PathGlob], path: &[S]) -> bool {
+    globs.iter().any(|glob| glob.covers(path))
+}
+
+/// True if one of `globs` covers the path or leads to it, which keeps it with `--only-path`.
+/// A path above a match is selected as well, since its subtree may hold the match.
+pub fn selects<S: AsRef<str>>(globs: &[PathGlob], path: &[S]) -> bool {
+    globs
+        .iter()
+        .any(|glob| glob.covers(path) || glob.leads_to(path))
+}
+
+fn leads_to<S: AsRef<str>>(pattern: &[Segment], path: &[S]) -> bool {
+    match (pattern.first(), path.first()) {
+        (Some(_), None) => true,
+        (Some(Segment::AnyDepth), Some(_)) => true,
+        (Some(Segment::Any), Some(_)) => leads_to(&pattern[1..], &path[1..]),
+        (Some(Segment::Literal(literal)), Some(segment)) => {
+            segment_matches(literal, segment.as_ref()) && leads_to(&pattern[1..], &path[1..])
+        }
+        (None, _) => false,
+    }
+}
+
+fn matches<S: AsRef<str>>(pattern: &[Segment], path: &[S]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(Segment::AnyDepth), _) => {
+            matches(&pattern[1..], path) || (!path.is_empty() && matches(pattern, &path[1..]))
+        }
+        (Some(Segment::Any), Some(_)) => matches(&pattern[1..], &path[1..]),
+        (Some(Segment::Literal(literal)), Some(segment)) => {
+            segment_matches(literal, segment.as_ref()) && matches(&pattern[1..], &path[1..])
+        }
+        _ => false,
+    }
+}
+
+fn segment_matches(literal: &str, segment: &str) -> bool {
+    if literal == segment {
+        return true;
+    }
+    let index = literal.parse::<usize>().ok();
+    index.is_some()
+        && (array_label_index(segment) == index || array_label_right_index(segment) == index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(s: &str) -> Vec<&str> {
+        s.split("->").collect()
+    }
+
+    #[test]
+    fn single_and_any_depth_wildcards() {
+        let glob: PathGlob = "items.*.updated_at".parse().unwrap();
+        assert!(glob.matches(&path("items->[l: 3]->updated_at")));
+        assert!(glob.matches(&path("items->x->updated_at")));
+        assert!(!glob.matches(&path("items->updated_at")));
+        assert!(!glob.matches(&path("items->a->b->updated_at")));
+
+        let glob: PathGlob = "a.**.id".parse().unwrap();
+        assert!(glob.matches(&path("a->id")));
+        assert!(glob.matches(&path("a->b->[l: 0]->id")));
+        assert!(!glob.matches(&path("b->id")));
+
+        let glob: PathGlob = "**".parse().unwrap();
+        assert!(glob.matches(&path("any->thing")));
+    }
+
+    #[test]
+    fn array_indices_and_ancestors() {
+        let glob: PathGlob = "list.1".parse().unwrap();
+        assert!(glob.matches(&path("list->[l: 1]")));
+        assert!(glob.matches(&path("list->[l: 1]-\"x\"")));
+        assert!(!glob.matches(&path("list->[l: 10]")));
+        assert!(!glob.matches(&path("list->[l: 1]->a")));
+        assert!(glob.covers(&path("list->[l: 1]->a")));
+        assert!(glob.matches(&path("list->[l: 1 r: 4]")));
+        assert!(glob.matches(&path("list->[l: 0 r: 1]")));
+        assert!(!glob.matches(&path("list->[l: 2 r: 3]")));
+    }
+
+    #[test]
+    fn paths_leading_to_matches() {
+        let glob: PathGlob = "a.*.b".parse().unwrap();
+        assert!(glob.leads_to(&path("a")));
+        assert!(glob.leads_to(&path("a->[l: 0]")));
+        assert!(!glob.leads_to(&path("a->[l: 0]->b")));
+        assert!(!glob.leads_to(&path("c")));
+        assert!("x.**"
+            .parse::<PathGlob>()
+            .unwrap()
+            .leads_to(&path("x->y->z")));
+    }
+
+    #[test]
+    fn ignore_rules() {
+        let rules = parse_ignore_rules("# volatile\n\nitems.*.updated_at\n  a.**.id  \n").unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                "items.*.updated_at".parse().unwrap(),
+                "a.**.id".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            parse_ignore_rules("a\n# b\nc..d"),
+            Err("line 3: empty segment in path pattern 'c..d'".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!("".parse::<PathGlob>().is_err());
+        assert!("a..b".parse::<PathGlob>().is_err());
+    }
+}
@@ -1,20 +1,320 @@
 use diffs::{myers, Diff, Replace};
+use indexmap::IndexMap;
+use regex::Regex;
 use std::borrow::Cow;
-use std::collections::HashMap;
-use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::hash::{Hash, Hasher};
 
-use crate::enums::Error;
+use crate::enums::{Error, JsonType, Which};
 use serde_json::Map;
 use serde_json::Value;
 
-use crate::ds::key_node::KeyNode;
-use crate::ds::mismatch::Mismatch;
+use crate::canonical::canonicalize;
+use crate::ds::key_node::{array_label_index, array_label_right_index, array_pair_label, KeyNode};
+use crate::ds::mismatch::{Mismatch, Move};
+use crate::ds::three_way::{ChangeOrigin, ThreeWayChange, ThreeWayMismatch};
+use crate::input::ensure_not_empty;
+use crate::options::{ArrayAlgorithm, DiffOptions};
 
-pub fn compare_jsons(a: &str, b: &str, sort_arrays: bool) -> Result<Mismatch, Error> {
-    let value1 = serde_json::from_str(a)?;
-    let value2 = serde_json::from_str(b)?;
-    Ok(match_json(&value1, &value2, sort_arrays))
+/// Parses both strings and compares them with `diff_values`, `at` restricts the comparison to
+/// the subtree at a JSON pointer
+pub fn compare_jsons(
+    a: &str,
+    b: &str,
+    options: &DiffOptions,
+    at: Option<&str>,
+) -> Result<Mismatch, Error> {
+    ensure_not_empty(a, Which::Left)?;
+    ensure_not_empty(b, Which::Right)?;
+    let value1 = serde_json::from_str(a).map_err(|err| Error::Json {
+        which: Which::Left,
+        err,
+    })?;
+    let value2 = serde_json::from_str(b).map_err(|err| Error::Json {
+        which: Which::Right,
+        err,
+    })?;
+    let (value1, value2) = match at {
+        Some(pointer) => select_subtrees(&value1, &value2, pointer)?,
+        None => (&value1, &value2),
+    };
+    Ok(diff_values(value1, value2, options))
+}
+
+/// Parses and compares two json documents without any side effect: nothing is printed, the
+/// process is never exited, and malformed or empty input is returned as `Err` instead of
+/// panicking.
+///
+/// ```
+/// use json_diff::diff_str;
+/// use json_diff::options::DiffOptions;
+///
+/// let options = DiffOptions::default();
+/// assert_eq!(diff_str(r#"{"a": 1}"#, r#"{"a": 2}"#, &options).unwrap().counts().changed, 1);
+/// assert!(diff_str("{", "{}", &options).is_err());
+/// ```
+pub fn diff_str(a: &str, b: &str, options: &DiffOptions) -> Result<Mismatch, Error> {
+    compare_jsons(a, b, options, None)
+}
+
+/// Navigates both values to the given JSON pointer, e.g. `/data/items/0`
+pub fn select_subtrees<'a>(
+    a: &'a Value,
+    b: &'a Value,
+    pointer: &str,
+) -> Result<(&'a Value, &'a Value), Error> {
+    match (a.pointer(pointer), b.pointer(pointer)) {
+        (Some(a), Some(b)) => Ok((a, b)),
+        (None, Some(_)) => Err(Error::PointerNotFound(pointer.to_string(), "first json")),
+        (Some(_), None) => Err(Error::PointerNotFound(pointer.to_string(), "second json")),
+        (None, None) => Err(Error::PointerNotFound(pointer.to_string(), "both jsons")),
+    }
+}
+
+pub fn compare_jsons_three_way(
+    base: &str,
+    mine: &str,
+    theirs: &str,
+    options: &DiffOptions,
+) -> Result<ThreeWayMismatch, Error> {
+    let parse = |json: &str, which: Which| {
+        ensure_not_empty(json, which)?;
+        serde_json::from_str(json).map_err(|err| Error::Json { which, err })
+    };
+    let base = parse(base, Which::Base)?;
+    let mine = parse(mine, Which::Mine)?;
+    let theirs = parse(theirs, Which::Theirs)?;
+    Ok(match_json_three_way(&base, &mine, &theirs, options))
+}
+
+/// Compares `mine` and `theirs` against a common `base` and reports which side changed each path.
+/// A path touched by both sides is a conflict if `mine` and `theirs` disagree on it.
+pub fn match_json_three_way(
+    base: &Value,
+    mine: &Value,
+    theirs: &Value,
+    options: &DiffOptions,
+) -> ThreeWayMismatch {
+    match_json_three_way_filtered(base, mine, theirs, options, |mismatch| mismatch)
+}
+
+/// Like [`match_json_three_way`], but only the differences of each side to the base that `filter`
+/// keeps are reported, e.g. [`Mismatch::retain`] to skip ignored paths
+pub fn match_json_three_way_filtered(
+    base: &Value,
+    mine: &Value,
+    theirs: &Value,
+    options: &DiffOptions,
+    filter: impl Fn(Mismatch) -> Mismatch,
+) -> ThreeWayMismatch {
+    fn leaf_paths(mismatch: &Mismatch) -> Vec<Vec<String>> {
+        let mut paths = mismatch.left_only_keys.leaf_paths();
+        paths.extend(mismatch.right_only_keys.leaf_paths());
+        paths.extend(mismatch.keys_in_both.leaf_paths());
+        paths.extend(mismatch.moved.iter().map(Move::element_path));
+        paths
+    }
+    fn overlaps(a: &[String], b: &[String]) -> bool {
+        a.iter().zip(b.iter()).all(|(a, b)| a == b)
+    }
+
+    let ours = leaf_paths(&filter(match_json_with_options(base, mine, options)));
+    let theirs_paths = leaf_paths(&filter(match_json_with_options(base, theirs, options)));
+    let disagreements = leaf_paths(&match_json_with_options(mine, theirs, options));
+
+    let classify = |path: &[String], other_side: &[Vec<String>], origin: ChangeOrigin| {
+        if !other_side.iter().any(|p| overlaps(p, path)) {
+            origin
+        } else if disagreements.iter().any(|p| overlaps(p, path)) {
+            ChangeOrigin::Conflict
+        } else {
+            ChangeOrigin::Both
+        }
+    };
+
+    let mut changes: Vec<ThreeWayChange> = ours
+        .iter()
+        .map(|path| {
+            ThreeWayChange::new(
+                path.clone(),
+                classify(path, &theirs_paths, ChangeOrigin::Ours),
+            )
+        })
+        .collect();
+    for path in theirs_paths.iter().filter(|p| !ours.contains(p)) {
+        changes.push(ThreeWayChange::new(
+            path.clone(),
+            classify(path, &ours, ChangeOrigin::Theirs),
+        ));
+    }
+    changes.sort_by_cached_key(|change| document_order(&change.path, [base, mine, theirs]));
+    ThreeWayMismatch::new(changes)
+}
+
+/// Sort key placing a path where it occurs in the documents: array elements by index and object
+/// keys by their position in the first document that has them
+fn document_order(path: &[String], documents: [&Value; 3]) -> Vec<(usize, usize)> {
+    let mut nodes = documents.map(Some);
+    path.iter()
+        .map(|segment| {
+            if let Some(index) = array_label_index(segment) {
+                let right = array_label_right_index(segment).unwrap_or(index);
+                // the base is the left side of both comparisons
+                nodes[0] = nodes[0].and_then(|node| node.get(index));
+                for node in &mut nodes[1..] {
+                    *node = node.and_then(|node| node.get(right));
+                }
+                return (0, index);
+            }
+            let position = nodes.iter().enumerate().find_map(|(document, node)| {
+                node.as_ref()?
+                    .as_object()?
+                    .keys()
+                    .position(|key| key == segment)
+                    .map(|position| (document, position))
+            });
+            for node in &mut nodes {
+                *node = node.and_then(|node| node.get(segment.as_str()));
+            }
+            position.unwrap_or((documents.len(), 0))
+        })
+        .collect()
+}
+/// Objects with at least this many common keys are compared in parallel
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 64;
+
+#[cfg(feature = "parallel")]
+fn map_keys<F>(keys: Vec<String>, f: F) -> Vec<(String, Mismatch)>
+where
+    F: Fn(&String) -> Mismatch + Sync,
+{
+    use rayon::prelude::*;
+    if keys.len() < PARALLEL_THRESHOLD {
+        return keys
+            .into_iter()
+            .map(|key| {
+                let child = f(&key);
+                (key, child)
+            })
+            .collect();
+    }
+    keys.into_par_iter()
+        .map(|key| {
+            let child = f(&key);
+            (key, child)
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn map_keys<F>(keys: Vec<String>, f: F) -> Vec<(String, Mismatch)>
+where
+    F: Fn(&String) -> Mismatch,
+{
+    keys.into_iter()
+        .map(|key| {
+            let child = f(&key);
+            (key, child)
+        })
+        .collect()
+}
+
+/// The first difference of two objects, see `DiffOptions::stop_at_first`. Keys only present on
+/// one side are checked before common keys are descended into.
+fn first_object_difference(
+    a: &Map<String, Value>,
+    b: &Map<String, Value>,
+    options: &DiffOptions,
+    path: Option<&Path>,
+) -> Mismatch {
+    let diff = intersect_maps(a, b, options);
+    let first = |keys: &Option<Vec<String>>| keys.as_ref().and_then(|keys| keys.first().cloned());
+    if let Some(key) = first(&diff.left_only) {
+        return Mismatch::new(get_map_of_keys(Some(vec![key])), KeyNode::Nil, KeyNode::Nil);
+    }
+    if let Some(key) = first(&diff.right_only) {
+        return Mismatch::new(KeyNode::Nil, get_map_of_keys(Some(vec![key])), KeyNode::Nil);
+    }
+    for key in diff.intersection.iter().flatten() {
+        let b_key = diff.right_key(key);
+        let child = match_at(&a[key], &b[b_key], options, Some(&Path::child(path, key)));
+        if !child.is_empty() {
+            return nest(child, key);
+        }
+    }
+    Mismatch::empty()
+}
+
+/// The first difference of two aligned arrays, see `DiffOptions::stop_at_first`
+fn first_array_difference(
+    alignment: ArrayAlignment,
+    options: &DiffOptions,
+    path: Option<&Path>,
+) -> Mismatch {
+    let ArrayAlignment {
+        a,
+        b,
+        replaced,
+        deleted,
+        inserted,
+        moved,
+        positions: (positions_a, positions_b),
+    } = alignment;
+    // positional alignment and uneven replacements leave runs of length zero
+    if let Some(&(i, _)) = deleted.iter().find(|(_, len)| *len > 0) {
+        let left_only = values_to_node(vec![(positions_a.of(i), a[i])]);
+        return Mismatch::new(left_only, KeyNode::Nil, KeyNode::Nil);
+    }
+    if let Some(&(i, _)) = inserted.iter().find(|(_, len)| *len > 0) {
+        let right_only = values_to_node(vec![(positions_b.of(i), b[i])]);
+        return Mismatch::new(KeyNode::Nil, right_only, KeyNode::Nil);
+    }
+    if let Some(&(from, to)) = moved.first() {
+        return Mismatch::empty().with_moves(vec![Move {
+            path: Path::segments(path),
+            from: positions_a.of(from),
+            to: positions_b.of(to),
+            value: a[from].clone(),
+        }]);
+    }
+    for (o, common, n, _) in replaced {
+        for i in 0..common {
+            let label = array_pair_label(positions_a.of(o + i), positions_b.of(n + i));
+            let child = match_at(
+                a[o + i],
+                b[n + i],
+                options,
+                Some(&Path::child(path, &label)),
+            );
+            if !child.is_empty() {
+                return nest(child, &label);
+            }
+        }
+    }
+    Mismatch::empty()
+}
+
+/// Puts the differences of a child value below its key, moves already carry their full path
+fn nest(child: Mismatch, key: &String) -> Mismatch {
+    let Mismatch {
+        left_only_keys,
+        right_only_keys,
+        keys_in_both,
+        moved,
+        equal_leaves,
+    } = child;
+    Mismatch::new(
+        insert_child_key_map(KeyNode::Nil, left_only_keys, key),
+        insert_child_key_map(KeyNode::Nil, right_only_keys, key),
+        insert_child_key_map(KeyNode::Nil, keys_in_both, key),
+    )
+    .with_moves(moved)
+    .with_equal_leaves(equal_leaves)
 }
+
 fn values_to_node(vec: Vec<(usize, &Value)>) -> KeyNode {
     if vec.is_empty() {
         KeyNode::Nil
@@ -45,332 +345,2199 @@ impl<'a> ListDiffHandler<'a> {
         }
     }
 }
+/// Only collects the ranges it is handed, so it cannot fail: its error type has no values,
+/// which lets `infallible` take the result apart without a panicking `unwrap`
 impl<'a> Diff for ListDiffHandler<'a> {
-    type Error = ();
-    fn delete(&mut self, old: usize, len: usize, _new: usize) -> Result<(), ()> {
+    type Error = Infallible;
+    fn delete(&mut self, old: usize, len: usize, _new: usize) -> Result<(), Infallible> {
         self.deletion.push((old, len));
         Ok(())
     }
-    fn insert(&mut self, _o: usize, new: usize, len: usize) -> Result<(), ()> {
+    fn insert(&mut self, _o: usize, new: usize, len: usize) -> Result<(), Infallible> {
         self.insertion.push((new, len));
         Ok(())
     }
-    fn replace(&mut self, old: usize, len: usize, new: usize, new_len: usize) -> Result<(), ()> {
+    fn replace(
+        &mut self,
+        old: usize,
+        len: usize,
+        new: usize,
+        new_len: usize,
+    ) -> Result<(), Infallible> {
         self.replaced.push((old, len, new, new_len));
         Ok(())
     }
 }
 
-pub fn match_json(value1: &Value, value2: &Value, sort_arrays: bool) -> Mismatch {
+/// The result of a diff run with `ListDiffHandler`, checked by the compiler to be `Ok`
+fn infallible(result: Result<(), Infallible>) {
+    match result {
+        Ok(()) => {}
+        Err(never) => match never {},
+    }
+}
+
+/// Compares two values that are already parsed, the entry point for callers holding
+/// `serde_json::Value`s; see `DiffOptions` for the available settings. `compare_jsons` does
+/// the same for json strings.
+/// `Value` cannot hold NaN or infinities (serde_json stores them as null), so they need no
+/// special casing here.
+///
+/// ```
+/// use json_diff::diff_values;
+/// use json_diff::options::DiffOptions;
+/// use serde_json::json;
+///
+/// let mismatch = diff_values(&json!({"a": 1}), &json!({"a": 2}), &DiffOptions::default());
+/// assert_eq!(mismatch.counts().changed, 1);
+/// ```
+pub fn diff_values(value1: &Value, value2: &Value, options: &DiffOptions) -> Mismatch {
+    match_at(value1, value2, options, None)
+}
+
+/// Compares two values with the default `DiffOptions`
+pub fn match_json(value1: &Value, value2: &Value) -> Mismatch {
+    diff_values(value1, value2, &DiffOptions::default())
+}
+
+/// Same as `diff_values`
+pub fn match_json_with_options(value1: &Value, value2: &Value, options: &DiffOptions) -> Mismatch {
+    diff_values(value1, value2, options)
+}
+
+/// Path from the root to the values being compared, linked towards the root so that
+/// descending into a child does not allocate
+struct Path<'a> {
+    parent: Option<&'a Path<'a>>,
+    key: &'a str,
+}
+
+impl<'a> Path<'a> {
+    fn child(parent: Option<&'a Path<'a>>, key: &'a str) -> Self {
+        Self { parent, key }
+    }
+
+    fn segments(path: Option<&Path>) -> Vec<String> {
+        let mut segments = Vec::new();
+        let mut current = path;
+        while let Some(path) = current {
+            segments.push(path.key.to_string());
+            current = path.parent;
+        }
+        segments.reverse();
+        segments
+    }
+}
+
+fn match_at(
+    value1: &Value,
+    value2: &Value,
+    options: &DiffOptions,
+    path: Option<&Path>,
+) -> Mismatch {
     match (value1, value2) {
+        (Value::Object(a), Value::Object(b)) if options.stop_at_first => {
+            first_object_difference(a, b, options, path)
+        }
         (Value::Object(a), Value::Object(b)) => {
-            let diff = intersect_maps(a, b);
-            let mut left_only_keys = get_map_of_keys(diff.left_only);
-            let mut right_only_keys = get_map_of_keys(diff.right_only);
-            let intersection_keys = diff.intersection;
+            let mut diff = intersect_maps(a, b, options);
+            let mut left_only_keys = get_map_of_keys(diff.left_only.take());
+            let mut right_only_keys = get_map_of_keys(diff.right_only.take());
+            let intersection_keys = diff.intersection.take();
 
             let mut unequal_keys = KeyNode::Nil;
+            let mut moved = Vec::new();
+            let mut equal_leaves = 0;
+            let has_leaves =
+                |map: &Map<String, Value>| map.iter().any(|(k, v)| is_counted(k, v, options));
+            if options.count_equal && !has_leaves(a) && !has_leaves(b) {
+                equal_leaves = 1;
+            }
 
             if let Some(intersection_keys) = intersection_keys {
-                for key in intersection_keys {
+                let children = map_keys(intersection_keys, |key| {
+                    let path = Path::child(path, key);
+                    match_at(&a[key], &b[diff.right_key(key)], options, Some(&path))
+                });
+                for (key, child) in children {
                     let Mismatch {
                         left_only_keys: l,
                         right_only_keys: r,
                         keys_in_both: u,
-                    } = match_json(a.get(&key).unwrap(), b.get(&key).unwrap(), sort_arrays);
+                        moved: m,
+                        equal_leaves: e,
+                    } = child;
                     left_only_keys = insert_child_key_map(left_only_keys, l, &key);
                     right_only_keys = insert_child_key_map(right_only_keys, r, &key);
                     unequal_keys = insert_child_key_map(unequal_keys, u, &key);
+                    moved.extend(m);
+                    equal_leaves += e;
                 }
             }
             Mismatch::new(left_only_keys, right_only_keys, unequal_keys)
+                .with_moves(moved)
+                .with_equal_leaves(equal_leaves)
+        }
+        (Value::Array(a), Value::Array(b)) if options.stop_at_first => {
+            first_array_difference(align_arrays(a, b, options), options, path)
         }
-        // this clearly needs to be improved! myers algorithm or whatever?
         (Value::Array(a), Value::Array(b)) => {
-            let a = preprocess_array(sort_arrays, a);
-            let b = preprocess_array(sort_arrays, b);
+            let ArrayAlignment {
+                a,
+                b,
+                replaced,
+                deleted,
+                inserted,
+                moved,
+                positions: (positions_a, positions_b),
+            } = align_arrays(a, b, options);
+
+            // elements outside of all runs are equal and compared no further
+            let mut equal_leaves = 0;
+            if options.count_equal {
+                let mut differs = vec![false; a.len()];
+                let runs = replaced.iter().map(|(o, ol, _, _)| (*o, *ol));
+                for (o, ol) in runs.chain(deleted.iter().copied()) {
+                    differs[o..o + ol].iter_mut().for_each(|d| *d = true);
+                }
+                for (o, _) in &moved {
+                    differs[*o] = true;
+                }
+                equal_leaves = (0..a.len())
+                    .filter(|i| !differs[*i])
+                    .map(|i| count_leaves(a[i], options))
+                    .sum();
+                if a.is_empty() && b.is_empty() {
+                    equal_leaves = 1;
+                }
+            }
+
+            fn extract_one_sided_values<'a>(
+                v: Vec<(usize, usize)>,
+                vals: &[&'a Value],
+                positions: &Positions,
+            ) -> Vec<(usize, &'a Value)> {
+                v.into_iter()
+                    .flat_map(|(o, ol)| (o..o + ol).map(|i| (positions.of(i), vals[i])))
+                    .collect::<Vec<(usize, &Value)>>()
+            }
+
+            let left_only_values: Vec<_> = extract_one_sided_values(deleted, &a[..], &positions_a);
+            let right_only_values: Vec<_> =
+                extract_one_sided_values(inserted, &b[..], &positions_b);
+
+            let mut left_only_nodes = values_to_node(left_only_values);
+            let mut right_only_nodes = values_to_node(right_only_values);
+            let mut diff = KeyNode::Nil;
+            let mut moved: Vec<Move> = moved
+                .into_iter()
+                .map(|(from, to)| Move {
+                    path: Path::segments(path),
+                    from: positions_a.of(from),
+                    to: positions_b.of(to),
+                    value: a[from].clone(),
+                })
+                .collect();
+
+            for (o, common, n, _) in replaced {
+                for i in 0..common {
+                    let inner_a = a[o + i];
+                    let inner_b = b[n + i];
+
+                    let label = array_pair_label(positions_a.of(o + i), positions_b.of(n + i));
+                    let cdiff =
+                        match_at(inner_a, inner_b, options, Some(&Path::child(path, &label)));
+                    let Mismatch {
+                        left_only_keys: l,
+                        right_only_keys: r,
+                        keys_in_both: u,
+                        moved: m,
+                        equal_leaves: e,
+                    } = cdiff;
+                    left_only_nodes = insert_child_key_map(left_only_nodes, l, &label);
+                    right_only_nodes = insert_child_key_map(right_only_nodes, r, &label);
+                    diff = insert_child_key_map(diff, u, &label);
+                    moved.extend(m);
+                    equal_leaves += e;
+                }
+            }
+
+            sort_by_index(&mut left_only_nodes);
+            sort_by_index(&mut right_only_nodes);
+            sort_by_index(&mut diff);
+            Mismatch::new(left_only_nodes, right_only_nodes, diff)
+                .with_moves(moved)
+                .with_equal_leaves(equal_leaves)
+        }
+        (a, b) => {
+            if let Some((a, b)) = parse_embedded(a, b, options) {
+                return match_at(&a, &b, options, path);
+            }
+            if scalars_equal(a, b, options, path) {
+                Mismatch::empty().with_equal_leaves(usize::from(options.count_equal))
+            } else {
+                Mismatch::new(
+                    KeyNode::Nil,
+                    KeyNode::Nil,
+                    KeyNode::Value(a.clone(), b.clone()),
+                )
+            }
+        }
+    }
+}
+
+/// Equality of two values that are not both objects or both arrays
+fn scalars_equal(a: &Value, b: &Value, options: &DiffOptions, path: Option<&Path>) -> bool {
+    a == b
+        || (options.keys_only && JsonType::from(a) == JsonType::from(b))
+        || (options.empty_equals_null && is_empty_or_null(a) && is_empty_or_null(b))
+        || match (a, b) {
+            (Value::String(a), Value::String(b)) => {
+                mask(&normalize(a, options), &options.masks)
+                    == mask(&normalize(b, options), &options.masks)
+            }
+            (Value::Number(a), Value::Number(b))
+                if options.numeric_exact && numbers_denote_same_value(a, b) =>
+            {
+                true
+            }
+            (Value::Number(a), Value::Number(b)) if !options.tolerances.is_empty() => {
+                numbers_within_tolerance(a, b, options, path)
+            }
+            (Value::String(s), other) | (other, Value::String(s)) if options.coerce_scalars => {
+                spells(s, other)
+            }
+            _ => false,
+        }
+}
+
+/// True if `s` is the json text of the number or bool `value`, numbers are compared by value
+/// so `"1.0"` spells `1`
+fn spells(s: &str, value: &Value) -> bool {
+    match value {
+        Value::Number(_) => s
+            .parse::<serde_json::Number>()
+            .is_ok_and(|n| compare_values(&Value::Number(n), value).is_eq()),
+        Value::Bool(b) => s == if *b { "true" } else { "false" },
+        _ => false,
+    }
+}
+
+/// True if both numbers have the same value. Integers and floats without a fractional part
+/// are compared as `i128`, so no integer is rounded, other floats by their `f64` value.
+#[cfg(not(feature = "arbitrary_precision"))]
+fn numbers_denote_same_value(a: &serde_json::Number, b: &serde_json::Number) -> bool {
+    let integer = |n: &serde_json::Number| {
+        n.as_i64()
+            .map(i128::from)
+            .or_else(|| n.as_u64().map(i128::from))
+            .or_else(|| {
+                let float = n.as_f64()?;
+                // every float of this magnitude is an integer that fits into an i128
+                (float.fract() == 0.0 && float.abs() < 2f64.powi(126)).then_some(float as i128)
+            })
+    };
+    match (integer(a), integer(b)) {
+        (Some(a), Some(b)) => a == b,
+        (None, None) => a.as_f64() == b.as_f64(),
+        _ => false,
+    }
+}
+
+/// True if both numbers have the same value, compared by their decimal text
+#[cfg(feature = "arbitrary_precision")]
+fn numbers_denote_same_value(a: &serde_json::Number, b: &serde_json::Number) -> bool {
+    let (a, b) = (decimal(a.as_str()), decimal(b.as_str()));
+    a.is_some() && a == b
+}
+
+/// Normal form of a decimal number text as sign, digits without leading or trailing zeros
+/// and the exponent of the last digit, so `-1.50e1` becomes `(true, "15", 0)`. Zero has no
+/// sign and no digits.
+#[cfg(feature = "arbitrary_precision")]
+fn decimal(text: &str) -> Option<(bool, String, i64)> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{integer}{fraction}");
+    let significant = digits.trim_start_matches('0').trim_end_matches('0');
+    if significant.is_empty() {
+        return Some((false, String::new(), 0));
+    }
+    let trailing_zeros = digits.len() - digits.trim_end_matches('0').len();
+    let exponent = exponent - fraction.len() as i64 + trailing_zeros as i64;
+    Some((negative, significant.to_string(), exponent))
+}
+
+/// Compares with the epsilon of the last tolerance rule matching the path, no rule means the
+/// numbers differ
+fn numbers_within_tolerance(
+    a: &serde_json::Number,
+    b: &serde_json::Number,
+    options: &DiffOptions,
+    path: Option<&Path>,
+) -> bool {
+    let segments = Path::segments(path);
+    let rule = options
+        .tolerances
+        .iter()
+        .rev()
+        .find(|rule| rule.path.matches(&segments));
+    match (rule, a.as_f64(), b.as_f64()) {
+        (Some(rule), Some(a), Some(b)) => options.tolerance_mode.within(a, b, rule.epsilon),
+        _ => false,
+    }
+}
+
+fn is_empty_or_null(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Object(map) => map.is_empty(),
+        Value::Array(values) => values.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "unicode")]
+fn normalize<'a>(s: &'a str, options: &DiffOptions) -> Cow<'a, str> {
+    match options.unicode_normalize {
+        Some(form) => Cow::Owned(form.normalize(s)),
+        None => Cow::Borrowed(s),
+    }
+}
+
+#[cfg(not(feature = "unicode"))]
+fn normalize<'a>(s: &'a str, _options: &DiffOptions) -> Cow<'a, str> {
+    Cow::Borrowed(s)
+}
+
+const MASK_PLACEHOLDER: &str = "<masked>";
+
+fn mask<'a>(s: &'a str, masks: &[Regex]) -> Cow<'a, str> {
+    masks.iter().fold(Cow::Borrowed(s), |s, mask| {
+        match mask.replace_all(&s, MASK_PLACEHOLDER) {
+            Cow::Borrowed(_) => s,
+            Cow::Owned(masked) => Cow::Owned(masked),
+        }
+    })
+}
+
+/// Both strings parsed, if `parse_embedded_json` is set and both differing strings hold json
+fn parse_embedded(a: &Value, b: &Value, options: &DiffOptions) -> Option<(Value, Value)> {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) if options.parse_embedded_json && a != b => {
+            Some((serde_json::from_str(a).ok()?, serde_json::from_str(b).ok()?))
+        }
+        _ => None,
+    }
+}
+
+/// Two arrays in comparison order with the runs that differ between them
+struct ArrayAlignment<'a> {
+    a: Vec<&'a Value>,
+    b: Vec<&'a Value>,
+    /// `(a index, a len, b index, b len)` runs of equal length that are compared pairwise
+    replaced: Vec<(usize, usize, usize, usize)>,
+    /// `(a index, len)` runs only on the left
+    deleted: Vec<(usize, usize)>,
+    /// `(b index, len)` runs only on the right
+    inserted: Vec<(usize, usize)>,
+    /// `(a index, b index)` of equal elements at different positions, taken out of `deleted`
+    /// and `inserted`; only filled with `detect_moves`
+    moved: Vec<(usize, usize)>,
+    /// Where the elements of `a` and `b` are in the compared arrays, for the labels
+    positions: (Positions, Positions),
+}
+
+/// Indices of the elements of an aligned array in the array it was taken from, which only
+/// differ once duplicates are dropped with `DiffOptions::array_dedup`
+struct Positions(Option<Vec<usize>>);
+
+impl Positions {
+    fn of(&self, index: usize) -> usize {
+        self.0.as_ref().map_or(index, |positions| positions[index])
+    }
+}
+
+/// Sorts both arrays if requested and finds the differing runs, elements outside of all runs
+/// are equal
+fn align_arrays<'a>(a: &'a [Value], b: &'a [Value], options: &DiffOptions) -> ArrayAlignment<'a> {
+    let sort = options.sort_arrays
+        || (options.sort_scalar_arrays && is_scalar_array(a) && is_scalar_array(b));
+    let sort_objects =
+        !sort && options.sort_object_arrays && is_object_array(a) && is_object_array(b);
+    let mut a = preprocess_array(sort, a);
+    let mut b = preprocess_array(sort, b);
+    if sort_objects {
+        sort_by_canonical_json(&mut a);
+        sort_by_canonical_json(&mut b);
+    }
+    let positions = if options.array_dedup {
+        let positions_a = dedup_array(&mut a);
+        let positions_b = dedup_array(&mut b);
+        (Positions(Some(positions_a)), Positions(Some(positions_b)))
+    } else {
+        (Positions(None), Positions(None))
+    };
 
-            let mut replaced = Vec::new();
-            let mut deleted = Vec::new();
-            let mut inserted = Vec::new();
+    let mut replaced = Vec::new();
+    let mut deleted = Vec::new();
+    let mut inserted = Vec::new();
 
+    let algorithm = if options.keys_only {
+        ArrayAlgorithm::Positional
+    } else {
+        options.array_algorithm
+    };
+    let above_threshold = options
+        .array_threshold
+        .is_some_and(|threshold| a.len() > threshold || b.len() > threshold);
+    match algorithm {
+        ArrayAlgorithm::Myers | ArrayAlgorithm::Lcs if above_threshold => {
+            let (only_a, only_b) = multiset_difference(&a, &b);
+            deleted.extend(only_a.into_iter().map(|i| (i, 1)));
+            inserted.extend(only_b.into_iter().map(|i| (i, 1)));
+        }
+        ArrayAlgorithm::Positional => {
+            let common = a.len().min(b.len());
+            replaced.push((0, common, 0, common));
+            deleted.push((common, a.len() - common));
+            inserted.push((common, b.len() - common));
+        }
+        ArrayAlgorithm::Myers => {
             let mut diff = Replace::new(ListDiffHandler::new(
                 &mut replaced,
                 &mut deleted,
                 &mut inserted,
             ));
-            myers::diff(
+            infallible(myers::diff(
                 &mut diff,
-                a.as_slice(),
+                &a[..],
                 0,
                 a.len(),
-                b.as_slice(),
+                &b[..],
                 0,
                 b.len(),
-            )
-            .unwrap();
+            ));
+        }
+        ArrayAlgorithm::Lcs => {
+            let mut diff = Replace::new(ListDiffHandler::new(
+                &mut replaced,
+                &mut deleted,
+                &mut inserted,
+            ));
+            infallible(lcs_diff(&mut diff, &a, &b));
+        }
+    }
+
+    // a replacement of unequal length is compared pairwise as far as both sides reach,
+    // the excess is a plain deletion or insertion
+    for (o, ol, n, nl) in replaced.iter_mut() {
+        let common = (*ol).min(*nl);
+        if *ol > common {
+            deleted.push((*o + common, *ol - common));
+        }
+        if *nl > common {
+            inserted.push((*n + common, *nl - common));
+        }
+        *ol = common;
+        *nl = common;
+    }
+
+    let moved = if options.detect_moves {
+        pair_moves(&a, &b, &mut deleted, &mut inserted)
+    } else {
+        Vec::new()
+    };
+
+    ArrayAlignment {
+        a,
+        b,
+        replaced,
+        deleted,
+        inserted,
+        moved,
+        positions,
+    }
+}
+
+/// Pairs removed elements of `a` with equal inserted elements of `b`, each element is paired
+/// at most once. Paired elements are taken out of `deleted` and `inserted`, which are left
+/// with runs of length one.
+fn pair_moves(
+    a: &[&Value],
+    b: &[&Value],
+    deleted: &mut Vec<(usize, usize)>,
+    inserted: &mut Vec<(usize, usize)>,
+) -> Vec<(usize, usize)> {
+    let expand = |runs: &[(usize, usize)]| -> Vec<usize> {
+        let mut indices: Vec<usize> = runs.iter().flat_map(|&(i, len)| i..i + len).collect();
+        indices.sort_unstable();
+        indices
+    };
+    let mut only_a = expand(deleted);
+    let mut only_b = expand(inserted);
+    let mut moved = Vec::new();
+    only_a.retain(|&i| match only_b.iter().position(|&j| a[i] == b[j]) {
+        Some(position) => {
+            moved.push((i, only_b.remove(position)));
+            false
+        }
+        None => true,
+    });
+    *deleted = only_a.into_iter().map(|i| (i, 1)).collect();
+    *inserted = only_b.into_iter().map(|j| (j, 1)).collect();
+    moved
+}
+
+/// Indices of the elements of `a` and `b` without an equal partner on the other side, each
+/// element matches at most once. Sorting and hashing the runs of elements that sort equal
+/// keep this at O(n log n) for any input.
+fn multiset_difference(a: &[&Value], b: &[&Value]) -> (Vec<usize>, Vec<usize>) {
+    let sorted = |values: &[&Value]| {
+        let mut indices: Vec<usize> = (0..values.len()).collect();
+        indices.sort_by(|&i, &j| compare_values(values[i], values[j]));
+        indices
+    };
+    let (sorted_a, sorted_b) = (sorted(a), sorted(b));
+    let (mut only_a, mut only_b) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < sorted_a.len() && j < sorted_b.len() {
+        match compare_values(a[sorted_a[i]], b[sorted_b[j]]) {
+            std::cmp::Ordering::Less => {
+                only_a.push(sorted_a[i]);
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => {
+                only_b.push(sorted_b[j]);
+                j += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                // the order does not tell all unequal values apart, e.g. 1 and 1.0, so the
+                // run of elements sorting equal is matched by actual equality
+                let run_a = sorted_a[i..]
+                    .iter()
+                    .take_while(|&&k| compare_values(a[k], a[sorted_a[i]]).is_eq())
+                    .count();
+                let run_b = sorted_b[j..]
+                    .iter()
+                    .take_while(|&&k| compare_values(b[k], a[sorted_a[i]]).is_eq())
+                    .count();
+                // equal values share a hash, so each element only looks at the unmatched
+                // elements of its bucket, and a run of equal values pairs in linear time
+                let mut unmatched_b: HashMap<u64, Vec<usize>> = HashMap::new();
+                for &l in &sorted_b[j..j + run_b] {
+                    unmatched_b.entry(value_hash(b[l])).or_default().push(l);
+                }
+                for &k in &sorted_a[i..i + run_a] {
+                    let bucket = unmatched_b.entry(value_hash(a[k])).or_default();
+                    match bucket.iter().position(|&l| a[k] == b[l]) {
+                        Some(position) => {
+                            bucket.swap_remove(position);
+                        }
+                        None => only_a.push(k),
+                    }
+                }
+                only_b.extend(unmatched_b.into_values().flatten());
+                i += run_a;
+                j += run_b;
+            }
+        }
+    }
+    only_a.extend(&sorted_a[i..]);
+    only_b.extend(&sorted_b[j..]);
+    only_a.sort_unstable();
+    only_b.sort_unstable();
+    (only_a, only_b)
+}
+
+/// Reports the edit script of a longest common subsequence of `a` and `b` to `d`, with all
+/// deletions between two equal runs before the insertions
+fn lcs_diff<D: Diff>(d: &mut D, a: &[&Value], b: &[&Value]) -> Result<(), D::Error> {
+    // lengths[i][j] is the length of a longest common subsequence of a[i..] and b[j..]
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        let (start_i, start_j) = (i, j);
+        while i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        }
+        if i > start_i {
+            d.equal(start_i, start_j, i - start_i)?;
+        }
+        let (start_i, start_j) = (i, j);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                break;
+            }
+            if j == b.len() || (i < a.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        if i > start_i {
+            d.delete(start_i, i - start_i, start_j)?;
+        }
+        if j > start_j {
+            d.insert(i, start_j, j - start_j)?;
+        }
+    }
+    d.finish()
+}
+
+/// How similar two values are, from `0.0` (nothing in common) to `1.0` (no differences).
+///
+/// The score is the number of matching leaves on both sides divided by the number of leaves
+/// on both sides. Leaves are scalars and empty objects or arrays; keys skipped through
+/// `ignore_keys` and, with `null_equals_missing`, keys holding `null` are not counted.
+/// An unchanged leaf counts once per side in both numbers. A changed value adds its leaves of
+/// both sides to the denominator only, as does a removed or added subtree for its one side.
+pub fn similarity(a: &Value, b: &Value, options: &DiffOptions) -> f64 {
+    let total = count_leaves(a, options) + count_leaves(b, options);
+    (2 * unchanged_leaves(a, b, options).len()) as f64 / total as f64
+}
+
+fn is_counted(key: &str, value: &Value, options: &DiffOptions) -> bool {
+    let ignored = options.ignore_keys.iter().any(|r| r.is_match(key));
+    let null_ignored = options.null_equals_missing && value.is_null();
+    !ignored && !null_ignored
+}
+
+fn count_leaves(value: &Value, options: &DiffOptions) -> usize {
+    let count = match value {
+        Value::Object(map) => map
+            .iter()
+            .filter(|(k, v)| is_counted(k, v, options))
+            .map(|(_, v)| count_leaves(v, options))
+            .sum(),
+        Value::Array(values) => values.iter().map(|v| count_leaves(v, options)).sum(),
+        _ => 1,
+    };
+    count.max(1)
+}
+
+/// Paths of the leaves that are equal on both sides, in the same terms as `similarity`:
+/// scalars and empty objects or arrays, without the keys skipped through the options.
+/// Array elements are labeled with their left index, like in the `Mismatch`.
+pub fn unchanged_leaves(a: &Value, b: &Value, options: &DiffOptions) -> Vec<Vec<String>> {
+    let mut paths = Vec::new();
+    collect_unchanged(a, b, options, None, &mut paths);
+    paths
+}
+
+fn collect_unchanged(
+    a: &Value,
+    b: &Value,
+    options: &DiffOptions,
+    path: Option<&Path>,
+    paths: &mut Vec<Vec<String>>,
+) {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut counted_a = a.iter().filter(|(k, v)| is_counted(k, v, options));
+            let mut counted_b = b.iter().filter(|(k, v)| is_counted(k, v, options));
+            if counted_a.next().is_none() && counted_b.next().is_none() {
+                paths.push(Path::segments(path));
+                return;
+            }
+            let diff = intersect_maps(a, b, options);
+            for key in diff.intersection.iter().flatten() {
+                let (value_a, value_b) = (&a[key], &b[diff.right_key(key)]);
+                if is_counted(key, value_a, options) && is_counted(key, value_b, options) {
+                    let path = Path::child(path, key);
+                    collect_unchanged(value_a, value_b, options, Some(&path), paths);
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            if a.is_empty() && b.is_empty() {
+                paths.push(Path::segments(path));
+                return;
+            }
+            let alignment = align_arrays(a, b, options);
+            for (i, paired) in unchanged_pairs(&alignment) {
+                let label = format!("[l: {}]", alignment.positions.0.of(i));
+                let path = Path::child(path, &label);
+                match paired {
+                    Some(j) => {
+                        let (a, b) = (alignment.a[i], alignment.b[j]);
+                        collect_unchanged(a, b, options, Some(&path), paths);
+                    }
+                    None => collect_leaves(alignment.a[i], options, Some(&path), paths),
+                }
+            }
+        }
+        (a, b) if scalars_equal(a, b, options, path) => paths.push(Path::segments(path)),
+        (a, b) => {
+            if let Some((a, b)) = parse_embedded(a, b, options) {
+                if match_at(&a, &b, options, path).is_empty() {
+                    paths.push(Path::segments(path));
+                }
+            }
+        }
+    }
+}
+
+/// Left indices of the elements that are equal on both sides, with `None`, and of the
+/// replaced elements that are compared to a right element, with its right index, in order
+fn unchanged_pairs(alignment: &ArrayAlignment) -> Vec<(usize, Option<usize>)> {
+    let mut pairs: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut differs = vec![false; alignment.a.len()];
+    for (o, common, n, _) in &alignment.replaced {
+        pairs.extend((0..*common).map(|i| (o + i, Some(n + i))));
+        differs[*o..o + common].iter_mut().for_each(|d| *d = true);
+    }
+    for (o, ol) in &alignment.deleted {
+        differs[*o..o + ol].iter_mut().for_each(|d| *d = true);
+    }
+    for (o, _) in &alignment.moved {
+        differs[*o] = true;
+    }
+    pairs.extend(
+        (0..differs.len())
+            .filter(|i| !differs[*i])
+            .map(|i| (i, None)),
+    );
+    pairs.sort_unstable();
+    pairs
+}
+
+/// The part of `a` that is equal in `b`, in the same terms as `unchanged_leaves`: objects keep
+/// the keys whose values have something in common, arrays the elements that are equal or have
+/// something in common with the element they are compared to, in their left order. Keys
+/// skipped through the options are left out. `None` if nothing is equal.
+pub fn common_value(a: &Value, b: &Value, options: &DiffOptions) -> Option<Value> {
+    common_at(a, b, options, None)
+}
+
+fn common_at(a: &Value, b: &Value, options: &DiffOptions, path: Option<&Path>) -> Option<Value> {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut counted_a = a.iter().filter(|(k, v)| is_counted(k, v, options));
+            let mut counted_b = b.iter().filter(|(k, v)| is_counted(k, v, options));
+            if counted_a.next().is_none() && counted_b.next().is_none() {
+                return Some(Value::Object(Map::new()));
+            }
+            let diff = intersect_maps(a, b, options);
+            let common: Map<String, Value> = diff
+                .intersection
+                .iter()
+                .flatten()
+                .filter_map(|key| {
+                    let (value_a, value_b) = (&a[key], &b[diff.right_key(key)]);
+                    if !is_counted(key, value_a, options) || !is_counted(key, value_b, options) {
+                        return None;
+                    }
+                    let path = Path::child(path, key);
+                    let common = common_at(value_a, value_b, options, Some(&path))?;
+                    Some((key.clone(), common))
+                })
+                .collect();
+            (!common.is_empty()).then_some(Value::Object(common))
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            if a.is_empty() && b.is_empty() {
+                return Some(Value::Array(Vec::new()));
+            }
+            let alignment = align_arrays(a, b, options);
+            let common: Vec<Value> = unchanged_pairs(&alignment)
+                .into_iter()
+                .filter_map(|(i, paired)| {
+                    let label = format!("[l: {}]", alignment.positions.0.of(i));
+                    let path = Path::child(path, &label);
+                    // an equal element is compared to itself to drop its skipped keys
+                    let b = paired.map_or(alignment.a[i], |j| alignment.b[j]);
+                    common_at(alignment.a[i], b, options, Some(&path))
+                })
+                .collect();
+            (!common.is_empty()).then_some(Value::Array(common))
+        }
+        (a, b) if scalars_equal(a, b, options, path) => Some(a.clone()),
+        (a, b) => {
+            let (parsed_a, parsed_b) = parse_embedded(a, b, options)?;
+            match_at(&parsed_a, &parsed_b, options, path)
+                .is_empty()
+                .then(|| a.clone())
+        }
+    }
+}
+
+/// Paths of all leaves of a value that is equal on both sides
+fn collect_leaves(
+    value: &Value,
+    options: &DiffOptions,
+    path: Option<&Path>,
+    paths: &mut Vec<Vec<String>>,
+) {
+    let before = paths.len();
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter().filter(|(k, v)| is_counted(k, v, options)) {
+                collect_leaves(value, options, Some(&Path::child(path, key)), paths);
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                let label = format!("[l: {i}]");
+                collect_leaves(value, options, Some(&Path::child(path, &label)), paths);
+            }
+        }
+        _ => {}
+    }
+    if paths.len() == before {
+        paths.push(Path::segments(path));
+    }
+}
+
+/// Sorts references to the elements, the elements themselves are never cloned
+fn preprocess_array(sort_arrays: bool, a: &[Value]) -> Vec<&Value> {
+    let mut refs: Vec<&Value> = a.iter().collect();
+    if sort_arrays {
+        refs.sort_by(|a, b| compare_values(a, b));
+    }
+    refs
+}
+
+fn is_scalar_array(a: &[Value]) -> bool {
+    a.iter()
+        .all(|v| !matches!(v, Value::Array(_) | Value::Object(_)))
+}
+
+fn is_object_array(a: &[Value]) -> bool {
+    a.iter().all(Value::is_object)
+}
+
+/// Sorts by the canonical serialization of each element, so objects with the same keys and
+/// values end up next to each other whatever their key order
+fn sort_by_canonical_json(values: &mut [&Value]) {
+    values.sort_by_cached_key(|value| canonicalize((*value).clone()).to_string());
+}
+
+/// Drops elements equal to an earlier one, the first occurrence keeps its place. Returns the
+/// index each kept element had before.
+fn dedup_array(values: &mut Vec<&Value>) -> Vec<usize> {
+    // equal values have the same hash, so only values sharing it are compared
+    let mut seen: HashMap<u64, Vec<&Value>> = HashMap::new();
+    let mut positions = Vec::new();
+    let mut index = 0;
+    values.retain(|value| {
+        let kept = seen.entry(value_hash(value)).or_default();
+        let first = !kept.contains(value);
+        if first {
+            kept.push(value);
+            positions.push(index);
+        }
+        index += 1;
+        first
+    });
+    positions
+}
+
+/// A hash that agrees with `Value` equality, so the keys of objects are hashed regardless of
+/// their order
+fn value_hash(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    std::mem::discriminant(value).hash(&mut hasher);
+    match value {
+        Value::Null => {}
+        Value::Bool(b) => b.hash(&mut hasher),
+        // the float zeros are equal but print differently
+        Value::Number(n) if n.is_f64() && n.as_f64() == Some(0.0) => "0.0".hash(&mut hasher),
+        Value::Number(n) => n.to_string().hash(&mut hasher),
+        Value::String(s) => s.hash(&mut hasher),
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| value_hash(value).hash(&mut hasher)),
+        Value::Object(map) => {
+            let entries = map.iter().fold(0u64, |sum, (key, value)| {
+                let mut entry = DefaultHasher::new();
+                key.hash(&mut entry);
+                value_hash(value).hash(&mut entry);
+                sum.wrapping_add(entry.finish())
+            });
+            entries.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Total order over values, for scalars: null < bool < number < string
+fn compare_values(a: &Value, b: &Value) -> std::cmp::Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => std::cmp::Ordering::Equal,
+        (Value::Null, _) => std::cmp::Ordering::Less,
+        (_, Value::Null) => std::cmp::Ordering::Greater,
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => {
+            // integers are compared exactly, an u64 beyond i64::MAX must not fall back to f64
+            let as_int = |n: &serde_json::Number| {
+                n.as_i64()
+                    .map(i128::from)
+                    .or_else(|| n.as_u64().map(i128::from))
+            };
+            if let (Some(a), Some(b)) = (as_int(a), as_int(b)) {
+                return a.cmp(&b);
+            }
+            if let (Some(a_f64), Some(b_f64)) = (a.as_f64(), b.as_f64()) {
+                let cmp = a_f64
+                    .partial_cmp(&b_f64)
+                    .unwrap_or(std::cmp::Ordering::Equal);
+                // decimals that round to the same f64 are still different numbers
+                #[cfg(feature = "arbitrary_precision")]
+                let cmp = cmp.then_with(|| a.as_str().cmp(b.as_str()));
+                return cmp;
+            }
+            // Handle other number types if needed
+            std::cmp::Ordering::Equal
+        }
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => {
+            for (a, b) in a.iter().zip(b.iter()) {
+                let cmp = compare_values(a, b);
+                if cmp != std::cmp::Ordering::Equal {
+                    return cmp;
+                }
+            }
+            a.len().cmp(&b.len())
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys_a: Vec<_> = a.keys().collect();
+            let mut keys_b: Vec<_> = b.keys().collect();
+            keys_a.sort();
+            keys_b.sort();
+            for (key_a, key_b) in keys_a.iter().zip(keys_b.iter()) {
+                let cmp = key_a.cmp(key_b);
+                if cmp != std::cmp::Ordering::Equal {
+                    return cmp;
+                }
+                let value_a = &a[*key_a];
+                let value_b = &b[*key_b];
+                let cmp = compare_values(value_a, value_b);
+                if cmp != std::cmp::Ordering::Equal {
+                    return cmp;
+                }
+            }
+            keys_a.len().cmp(&keys_b.len())
+        }
+        (Value::Object(_), _) => std::cmp::Ordering::Less,
+        (_, Value::Object(_)) => std::cmp::Ordering::Greater,
+        (Value::Bool(_), _) => std::cmp::Ordering::Less,
+        (_, Value::Bool(_)) => std::cmp::Ordering::Greater,
+        (Value::Number(_), _) => std::cmp::Ordering::Less,
+        (_, Value::Number(_)) => std::cmp::Ordering::Greater,
+        (Value::String(_), _) => std::cmp::Ordering::Less,
+        (_, Value::String(_)) => std::cmp::Ordering::Greater,
+    }
+}
+
+/// Array entries are collected per kind of change, this puts them back into index order
+fn sort_by_index(node: &mut KeyNode) {
+    fn index(key: &str) -> usize {
+        array_label_index(key).unwrap_or(usize::MAX)
+    }
+    if let KeyNode::Node(map) = node {
+        map.sort_by(|a, _, b, _| index(a).cmp(&index(b)));
+    }
+}
+
+fn get_map_of_keys(keys: Option<Vec<String>>) -> KeyNode {
+    if let Some(keys) = keys {
+        KeyNode::Node(keys.into_iter().map(|key| (key, KeyNode::Nil)).collect())
+    } else {
+        KeyNode::Nil
+    }
+}
+
+fn insert_child_key_map(parent: KeyNode, child: KeyNode, key: &String) -> KeyNode {
+    if child == KeyNode::Nil {
+        return parent;
+    }
+    if let KeyNode::Node(mut map) = parent {
+        map.insert(String::from(key), child);
+        KeyNode::Node(map) // This is weird! I just wanted to return back `parent` here
+    } else if let KeyNode::Nil = parent {
+        let mut map = IndexMap::new();
+        map.insert(String::from(key), child);
+        KeyNode::Node(map)
+    } else {
+        parent // TODO Trying to insert child node in a Value variant : Should not happen => Throw an error instead.
+    }
+}
+
+struct MapDifference {
+    left_only: Option<Vec<String>>,
+    right_only: Option<Vec<String>>,
+    /// Common keys as they are named in the left map
+    intersection: Option<Vec<String>>,
+    /// Common keys that are named differently in the right map, with `ignore_key_case`
+    renamed: HashMap<String, String>,
+}
+
+impl MapDifference {
+    pub fn new(
+        left_only: Option<Vec<String>>,
+        right_only: Option<Vec<String>>,
+        intersection: Option<Vec<String>>,
+    ) -> Self {
+        Self {
+            right_only,
+            left_only,
+            intersection,
+            renamed: HashMap::new(),
+        }
+    }
+
+    /// The name in the right map of a common key
+    fn right_key<'a>(&'a self, key: &'a String) -> &'a String {
+        self.renamed.get(key).unwrap_or(key)
+    }
+}
+
+fn intersect_maps(
+    a: &Map<String, Value>,
+    b: &Map<String, Value>,
+    options: &DiffOptions,
+) -> MapDifference {
+    let mut intersection = Vec::new();
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    let is_ignored = |key: &String| options.ignore_keys.iter().any(|r| r.is_match(key));
+    let is_null_ignored = |value: &Value| options.null_equals_missing && value.is_null();
+    // keys of b without an exact match in a by their lowercase form, in document order
+    let mut by_case: HashMap<String, VecDeque<&String>> = HashMap::new();
+    if options.ignore_key_case {
+        for b_key in b.keys().filter(|k| !is_ignored(k) && !a.contains_key(*k)) {
+            by_case
+                .entry(b_key.to_lowercase())
+                .or_default()
+                .push_back(b_key);
+        }
+    }
+    let mut renamed = HashMap::new();
+    for (a_key, a_value) in a.iter().filter(|(k, _)| !is_ignored(k)) {
+        if b.contains_key(a_key) {
+            intersection.push(String::from(a_key));
+        } else if let Some(b_key) = by_case
+            .get_mut(&a_key.to_lowercase())
+            .and_then(VecDeque::pop_front)
+        {
+            intersection.push(String::from(a_key));
+            renamed.insert(String::from(a_key), String::from(b_key));
+        } else if !is_null_ignored(a_value) {
+            left.push(String::from(a_key));
+        }
+    }
+    if !options.subset {
+        let paired: HashSet<&String> = renamed.values().collect();
+        for (b_key, b_value) in b.iter().filter(|(k, _)| !is_ignored(k)) {
+            if !a.contains_key(b_key) && !paired.contains(b_key) && !is_null_ignored(b_value) {
+                right.push(String::from(b_key));
+            }
+        }
+    }
+    let left = if left.is_empty() { None } else { Some(left) };
+    let right = if right.is_empty() { None } else { Some(right) };
+    let intersection = if intersection.is_empty() {
+        None
+    } else {
+        Some(intersection)
+    };
+    MapDifference {
+        renamed,
+        ..MapDifference::new(left, right, intersection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::{ChangeKind, JsonType};
+    use crate::options::{Tolerance, ToleranceMode};
+    use indexmap::indexmap;
+    use serde_json::json;
+
+    #[test]
+    fn test_arrays_sorted_simple() {
+        let data1 = r#"["a","b","c"]"#;
+        let data2 = r#"["b","c","a"]"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_arrays_sorted_objects() {
+        let data1 = r#"[{"c": {"d": "e"} },"b","c"]"#;
+        let data2 = r#"["b","c",{"c": {"d": "e"} }]"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_arrays_deep_sorted_objects() {
+        let data1 = r#"[{"c": ["d","e"] },"b","c"]"#;
+        let data2 = r#"["b","c",{"c": ["e", "d"] }]"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_arrays_deep_sorted_objects_with_outer_diff() {
+        let data1 = r#"[{"c": ["d","e"] },"b"]"#;
+        let data2 = r#"["b","c",{"c": ["e", "d"] }]"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(!diff.is_empty());
+        let insertions = diff.right_only_keys.absolute_keys_to_vec(None);
+        assert_eq!(insertions.len(), 1);
+        assert_eq!(insertions.first().unwrap().to_string(), r#"[l: 2]-"c""#);
+    }
+
+    #[test]
+    fn test_arrays_deep_sorted_objects_with_inner_diff() {
+        let data1 = r#"["a",{"c": ["d","e", "f"] },"b"]"#;
+        let data2 = r#"["b",{"c": ["e","d"] },"a"]"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(!diff.is_empty());
+        let deletions = diff.left_only_keys.absolute_keys_to_vec(None);
+
+        assert_eq!(deletions.len(), 1);
+        assert_eq!(
+            deletions.first().unwrap().to_string(),
+            r#"[l: 0]->c->[l: 2]-"f""#
+        );
+    }
+
+    #[test]
+    fn test_arrays_deep_sorted_objects_with_inner_diff_mutation() {
+        let data1 = r#"["a",{"c": ["d", "f"] },"b"]"#;
+        let data2 = r#"["b",{"c": ["e","d"] },"a"]"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(!diff.is_empty());
+        let diffs = diff.keys_in_both.absolute_keys_to_vec(None);
+
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(
+            diffs.first().unwrap().to_string(),
+            r#"[l: 0]->c->[l: 1]->{"f"!="e"}"#
+        );
+    }
+
+    #[test]
+    fn test_scalar_arrays_sorted() {
+        let data1 = r#"{"a": [3, "x", null, true, 1]}"#;
+        let data2 = r#"{"a": [true, 1, null, "x", 3]}"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_scalar_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_object_arrays_sorted() {
+        let options = DiffOptions::default().sort_object_arrays(true);
+        let a = json!({"rows": [{"id": 1, "x": "a"}, {"x": "b", "id": 2}, {"id": 1, "x": "a"}]});
+        let b = json!({"rows": [{"x": "b", "id": 2}, {"id": 1, "x": "a"}, {"x": "a", "id": 1}]});
+        assert!(!match_json(&a, &b).is_empty());
+        assert!(match_json_with_options(&a, &b, &options).is_empty());
+
+        // duplicates match by multiplicity, the extra copy is removed
+        let c = json!({"rows": [{"id": 1, "x": "a"}, {"x": "b", "id": 2}]});
+        let diff = match_json_with_options(&a, &c, &options);
+        assert_eq!(diff.counts().removed, 1);
+        assert_eq!(diff.counts().changed, 0);
+
+        // arrays that are not all objects keep their order
+        let diff = match_json_with_options(&json!([{"a": 1}, 2]), &json!([2, {"a": 1}]), &options);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_scalar_arrays_sorting_skips_object_arrays() {
+        let data1 = r#"[{"a": 1}, {"b": 2}]"#;
+        let data2 = r#"[{"b": 2}, {"a": 1}]"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_scalar_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(!diff.is_empty());
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_ignore_keys_by_regex() {
+        let data1 = r#"{"id": 1, "meta": {"created_at": 1, "x": 1}, "items": [{"updated_at": 1}]}"#;
+        let data2 = r#"{"id": 2, "meta": {"created_at": 2, "x": 1}, "items": [{"updated_at": 2}]}"#;
+        let ignore = [Regex::new(r"_at$").unwrap()];
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().ignore_keys(ignore.to_vec()),
+            None,
+        )
+        .unwrap();
+        let diffs = diff.keys_in_both.absolute_keys_to_vec(None);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs.first().unwrap().to_string(), "id->{1!=2}");
+        assert_eq!(diff.left_only_keys, KeyNode::Nil);
+        assert_eq!(diff.right_only_keys, KeyNode::Nil);
+    }
+
+    #[test]
+    fn test_ignore_keys_skips_subtree_and_one_sided_keys() {
+        let data1 = r#"{"volatile": {"a": 1}, "left": 1}"#;
+        let data2 = r#"{"volatile": {"a": 2, "b": 3}}"#;
+        let ignore = [Regex::new(r"^(volatile|left)$").unwrap()];
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().ignore_keys(ignore.to_vec()),
+            None,
+        )
+        .unwrap();
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_compare_at_pointer() {
+        let data1 = r#"{"data": {"items": [1, 2]}, "other": 1}"#;
+        let data2 = r#"{"data": {"items": [1, 3]}, "other": 2}"#;
+        let diff =
+            compare_jsons(data1, data2, &DiffOptions::default(), Some("/data/items")).unwrap();
+        let diffs = diff.keys_in_both.absolute_keys_to_vec(None);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs.first().unwrap().to_string(), "[l: 1]->{2!=3}");
+    }
+
+    #[test]
+    fn test_compare_at_missing_pointer() {
+        let data1 = r#"{"data": {}}"#;
+        let data2 = r#"{"data": {"items": []}}"#;
+        let result = compare_jsons(data1, data2, &DiffOptions::default(), Some("/data/items"));
+        assert!(matches!(
+            result,
+            Err(Error::PointerNotFound(_, "first json"))
+        ));
+        let result = compare_jsons(data1, data2, &DiffOptions::default(), Some("/nope"));
+        assert!(matches!(
+            result,
+            Err(Error::PointerNotFound(_, "both jsons"))
+        ));
+    }
+
+    #[test]
+    fn test_large_integers_are_compared_exactly() {
+        let data1 = r#"{"a": 9007199254740993, "b": -9007199254740993}"#;
+        let data2 = r#"{"a": 9007199254740992, "b": -9007199254740992}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        assert_eq!(diff.keys_in_both.count_leaves(), 2);
+
+        let data1 = r#"[18446744073709551615, 18446744073709551614, 1]"#;
+        let data2 = r#"[1, 18446744073709551614, 18446744073709551615]"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().sort_arrays(true),
+            None,
+        )
+        .unwrap();
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_ignore_key_case() {
+        let options = DiffOptions::default().ignore_key_case(true);
+        let a = json!({"UserName": "ann", "Address": {"City": "x"}, "id": 1});
+        let b = json!({"username": "ann", "address": {"city": "y"}, "id": 1});
+        assert_eq!(match_json(&a, &b).counts().added, 2);
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(
+            diff.all_diffs()
+                .into_iter()
+                .map(|(_, key)| key.to_string())
+                .collect::<Vec<_>>(),
+            vec![r#"Address->City->{"x"!="y"}"#]
+        );
+        assert!(unchanged_leaves(&a, &b, &options).contains(&vec!["UserName".to_string()]));
+
+        // exact matches pair first, the rest in document order
+        let a = json!({"Name": 1, "name": 2});
+        let b = json!({"NAME": 1, "name": 2, "nAmE": 3});
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(diff.counts().changed, 0);
+        assert_eq!(
+            diff.right_only_keys.leaf_paths(),
+            vec![vec!["nAmE".to_string()]]
+        );
+        let options = options.stop_at_first(true);
+        assert!(match_json_with_options(&json!({"A": 1}), &json!({"a": 1}), &options).is_empty());
+    }
+
+    #[test]
+    fn test_null_equals_missing() {
+        let data1 = r#"{"a": null, "b": {"c": 1, "d": null}, "e": 1}"#;
+        let data2 = r#"{"b": {"c": 1}, "f": null, "e": null}"#;
+        let diff = compare_jsons(
+            data1,
+            data2,
+            &DiffOptions::default().null_equals_missing(true),
+            None,
+        )
+        .unwrap();
+        assert_eq!(diff.left_only_keys, KeyNode::Nil);
+        assert_eq!(diff.right_only_keys, KeyNode::Nil);
+        assert_eq!(diff.keys_in_both.leaf_paths(), vec![vec!["e".to_string()]]);
+
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        assert_eq!(diff.left_only_keys.count_leaves(), 2);
+        assert_eq!(diff.right_only_keys.count_leaves(), 1);
+    }
+
+    #[test]
+    fn test_keys_only() {
+        let data1 = r#"{"a": 1, "b": [1, 2, {"c": "x"}], "d": "s", "e": 1}"#;
+        let data2 = r#"{"a": 2, "b": [3, 4, {"c": "y"}, 5], "d": 1, "f": 1}"#;
+        let diff =
+            compare_jsons(data1, data2, &DiffOptions::default().keys_only(true), None).unwrap();
+        assert_eq!(diff.keys_in_both.leaf_paths(), vec![vec!["d".to_string()]]);
+        assert_eq!(
+            diff.right_only_keys.leaf_paths().len(),
+            2,
+            "extra array element and key f"
+        );
+        assert_eq!(
+            diff.left_only_keys.leaf_paths(),
+            vec![vec!["e".to_string()]]
+        );
+    }
+
+    #[test]
+    fn test_large_objects() {
+        let left: Map<String, Value> = (0..200).map(|i| (format!("k{i}"), json!(i))).collect();
+        let mut right = left.clone();
+        right.insert("k7".to_string(), json!("changed"));
+        right.insert("k150".to_string(), json!({"nested": true}));
+        right.remove("k99");
+        right.insert("new".to_string(), json!(1));
+        let diff = match_json(&Value::Object(left), &Value::Object(right));
+        let mut changed = diff.keys_in_both.leaf_paths();
+        changed.sort();
+        assert_eq!(
+            changed,
+            vec![vec!["k150".to_string()], vec!["k7".to_string()]]
+        );
+        assert_eq!(
+            diff.left_only_keys.leaf_paths(),
+            vec![vec!["k99".to_string()]]
+        );
+        assert_eq!(
+            diff.right_only_keys.leaf_paths(),
+            vec![vec!["new".to_string()]]
+        );
+    }
+
+    #[test]
+    fn test_keys_containing_separator_are_quoted() {
+        let data1 = r#"{"a->b": 1, "a": {"b": 1}, "\"q": [1]}"#;
+        let data2 = r#"{"a->b": 2, "a": {"b": 2}, "\"q": [1, "x->y"]}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let keys: Vec<String> = diff
+            .all_diffs()
+            .into_iter()
+            .map(|(_, key)| key.to_string())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                r#""a->b"->{1!=2}"#,
+                "a->b->{1!=2}",
+                r#""\"q"->[l: 1]-"x->y""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subset() {
+        let a = json!({"a": 1, "b": {"c": [1, 2]}});
+        let b = json!({"a": 1, "b": {"c": [1, 2], "d": 3}, "e": 4});
+        let options = DiffOptions::default().subset(true);
+        assert!(match_json_with_options(&a, &b, &options).is_empty());
+        assert!(!match_json(&a, &b).is_empty());
+
+        let diff = match_json_with_options(&b, &a, &options);
+        assert_eq!(diff.counts().removed, 2);
+        assert_eq!(diff.right_only_keys, KeyNode::Nil);
+
+        let a = json!({"a": 1, "b": [1], "c": 1});
+        let b = json!({"a": 2, "b": [1, 2], "x": 1});
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(diff.keys_in_both.absolute_keys_to_vec(None).len(), 1);
+        assert_eq!(
+            diff.left_only_keys.leaf_paths(),
+            vec![vec!["c".to_string()]]
+        );
+        assert_eq!(diff.right_only_keys.leaf_paths().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_embedded_json() {
+        let a = json!({"payload": "{\"x\": 1, \"y\": [1, 2]}", "text": "a"});
+        let b = json!({"payload": "{\"y\":[1,2],\"x\":2}", "text": "b"});
+        let options = DiffOptions::default().parse_embedded_json(true);
+        let diffs: Vec<String> = match_json_with_options(&a, &b, &options)
+            .all_diffs()
+            .into_iter()
+            .map(|(_, key)| key.to_string())
+            .collect();
+        assert_eq!(diffs, vec!["payload->x->{1!=2}", r#"text->{"a"!="b"}"#]);
+
+        let a = json!({"payload": "{\"x\": 1}"});
+        let b = json!({"payload": "{\"x\":1}"});
+        assert!(match_json_with_options(&a, &b, &options).is_empty());
+        assert_eq!(similarity(&a, &b, &options), 1.0);
+        assert!(!match_json(&a, &b).is_empty());
+    }
+
+    #[test]
+    fn test_masks() {
+        let a = json!({"at": "2024-01-02T10:00:00", "id": "x-1", "list": ["2024-01-02"]});
+        let b = json!({"at": "2023-12-31T10:00:00", "id": "y-1", "list": ["1999-09-09"]});
+        let options = DiffOptions::default().masks(vec![Regex::new(r"\d{4}-\d{2}-\d{2}").unwrap()]);
+        let diffs: Vec<String> = match_json_with_options(&a, &b, &options)
+            .all_diffs()
+            .into_iter()
+            .map(|(_, key)| key.to_string())
+            .collect();
+        assert_eq!(diffs, vec![r#"id->{"x-1"!="y-1"}"#]);
+
+        let a = json!({"n": 2024, "t": "10:00"});
+        let b = json!({"n": 2023, "t": "11:00"});
+        let options = DiffOptions::default().masks(vec![Regex::new(r"\d+").unwrap()]);
+        assert_eq!(
+            match_json_with_options(&a, &b, &options).counts().changed,
+            1
+        );
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_unicode_normalize() {
+        use crate::options::UnicodeForm;
+        let a = json!({"name": "caf\u{e9}", "list": ["e\u{301}"]});
+        let b = json!({"name": "cafe\u{301}", "list": ["\u{e9}"]});
+        assert_eq!(match_json(&a, &b).counts().changed, 2);
+        for form in [UnicodeForm::Nfc, UnicodeForm::Nfd] {
+            let options = DiffOptions::default().unicode_normalize(Some(form));
+            assert!(match_json_with_options(&a, &b, &options).is_empty());
+        }
+        let options = DiffOptions::default().unicode_normalize(Some(UnicodeForm::Nfc));
+        let diff = match_json_with_options(&json!(["e\u{301}"]), &json!(["e"]), &options);
+        assert_eq!(diff.counts().changed, 1);
+    }
+
+    #[test]
+    fn test_stop_at_first() {
+        let options = DiffOptions::default().stop_at_first(true);
+        let total = |a: &Value, b: &Value| {
+            let counts = match_json_with_options(a, b, &options).counts();
+            counts.added + counts.removed + counts.changed + counts.moved
+        };
+        let a = json!({"a": 1, "b": {"c": [1, 2, 3], "d": "x"}, "e": true});
+        assert_eq!(total(&a, &a.clone()), 0);
+
+        let b = json!({"a": 2, "b": {"c": [1, 5, 3], "d": "y"}, "e": true});
+        assert_eq!(total(&a, &b), 1);
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(diff.all_diffs()[0].1.to_string(), "a->{1!=2}");
+
+        let b = json!({"a": 1, "b": {"c": [1, 3, 4], "d": "x"}, "f": true});
+        assert_eq!(total(&a, &b), 1);
+        let b = json!({"a": 1, "b": {"c": [0, 1, 2, 3], "d": "x"}, "e": true});
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(diff.all_diffs()[0].1.to_string(), "b->c->[l: 0]-0");
+        let b = json!({"a": 1, "b": {"c": [1, 2, 4], "d": "x"}, "e": true});
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(diff.all_diffs()[0].1.to_string(), "b->c->[l: 2]->{3!=4}");
+
+        let moves = options.clone().detect_moves(true);
+        let diff = match_json_with_options(&json!([1, 2, 3]), &json!([2, 3, 1]), &moves);
+        assert_eq!(diff.moved.len(), 1);
+
+        // positional alignment of arrays of equal length leaves empty deletions
+        let positional = options.array_algorithm(ArrayAlgorithm::Positional);
+        let diff = match_json_with_options(&json!([1, 2]), &json!([1, 3]), &positional);
+        assert_eq!(diff.all_diffs()[0].1.to_string(), "[l: 1]->{2!=3}");
+    }
+
+    #[test]
+    fn test_array_dedup() {
+        let options = DiffOptions::default().array_dedup(true);
+        let (a, b) = (json!([1, 2, 1, 3, 2]), json!([1, 2, 3, 3]));
+        assert!(match_json_with_options(&a, &b, &options).is_empty());
+        assert!(!match_json(&a, &b).is_empty());
+
+        // the order of the first occurrences still counts
+        let diff = match_json_with_options(&json!([1, 2, 1]), &json!([2, 1, 2]), &options);
+        assert!(!diff.is_empty());
+
+        let a = json!([{"a": 1, "b": [2]}, {"b": [2], "a": 1}, 1.0]);
+        let b = json!([{"a": 1, "b": [2]}, 1.0, 1]);
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(diff.right_only_keys.leaf_paths(), vec![vec!["[l: 2]-1"]]);
+
+        // with arbitrary precision numbers compare by their text, so the zeros differ
+        if cfg!(not(feature = "arbitrary_precision")) {
+            let (a, b) = (json!([-0.0, 0.0, 1]), json!([0.0, 1]));
+            assert!(match_json_with_options(&a, &b, &options).is_empty());
+        }
+    }
+
+    #[test]
+    fn array_dedup_reports_source_indices() {
+        let options = DiffOptions::default().array_dedup(true);
+        let diff = match_json_with_options(&json!([1, 1, 2]), &json!([1, 1, 3]), &options);
+        assert_eq!(diff.keys_in_both.leaf_paths(), vec![vec!["[l: 2]"]]);
+
+        let diff = match_json_with_options(&json!([1, 1, 2, 4]), &json!([1, 3, 3, 4, 5]), &options);
+        assert_eq!(diff.keys_in_both.leaf_paths(), vec![vec!["[l: 2 r: 1]"]]);
+        assert_eq!(diff.right_only_keys.leaf_paths(), vec![vec!["[l: 4]-5"]]);
+
+        let options = options.detect_moves(true);
+        let diff = match_json_with_options(&json!([1, 1, 2, 3]), &json!([3, 3, 1, 2]), &options);
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!((diff.moved[0].from, diff.moved[0].to), (3, 0));
+
+        let options = DiffOptions::default().array_dedup(true).stop_at_first(true);
+        let diff = match_json_with_options(&json!([1, 1, 2]), &json!([1, 1]), &options);
+        assert_eq!(diff.left_only_keys.leaf_paths(), vec![vec!["[l: 2]-2"]]);
+    }
+
+    #[test]
+    fn test_coerce_scalars() {
+        let options = DiffOptions::default().coerce_scalars(true);
+        let equal = [
+            (json!("1"), json!(1)),
+            (json!(1), json!("1")),
+            (json!("1.5"), json!(1.5)),
+            (json!("true"), json!(true)),
+            (json!(false), json!("false")),
+        ];
+        for (a, b) in &equal {
+            assert!(!match_json(a, b).is_empty(), "{a} {b}");
+            assert!(
+                match_json_with_options(a, b, &options).is_empty(),
+                "{a} {b}"
+            );
+        }
+        let different = [
+            (json!("abc"), json!(1)),
+            (json!("1"), json!(2)),
+            (json!(" 1"), json!(1)),
+            (json!("True"), json!(true)),
+            (json!("null"), json!(null)),
+            (json!("a"), json!("b")),
+        ];
+        for (a, b) in &different {
+            assert!(
+                !match_json_with_options(a, b, &options).is_empty(),
+                "{a} {b}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_equals_null() {
+        let options = DiffOptions::default().empty_equals_null(true);
+        let empties = [json!({}), json!([]), json!(null)];
+        for a in &empties {
+            for b in &empties {
+                assert!(
+                    match_json_with_options(a, b, &options).is_empty(),
+                    "{a} {b}"
+                );
+                assert_eq!(similarity(a, b, &options), 1.0);
+            }
+        }
+        assert!(!match_json(&json!({}), &json!(null)).is_empty());
+        assert!(!match_json(&json!([]), &json!({})).is_empty());
+
+        for other in [
+            json!(0),
+            json!(""),
+            json!(false),
+            json!([null]),
+            json!({"a": null}),
+        ] {
+            for empty in &empties {
+                assert!(!match_json_with_options(&other, empty, &options).is_empty());
+                assert!(!match_json_with_options(empty, &other, &options).is_empty());
+            }
+        }
+
+        let a = json!({"tags": [], "meta": null, "n": 0});
+        let b = json!({"tags": null, "meta": {}, "n": null});
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(diff.keys_in_both.leaf_paths(), vec![vec!["n".to_string()]]);
+    }
+
+    #[test]
+    fn test_tolerance_per_path() {
+        let tolerances = ["**.price=0.01", "geo.*=0.000001", "geo.lat=0"]
+            .iter()
+            .map(|t| t.parse().unwrap())
+            .collect();
+        let options = DiffOptions::default().tolerances(tolerances);
+        let a = json!({"items": [{"price": 1.0, "count": 1}], "geo": {"lat": 1.0, "lon": 2.0}});
+        let b = json!({"items": [{"price": 1.005, "count": 2}], "geo": {"lat": 1.0000001, "lon": 2.0000001}});
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(
+            diff.keys_in_both.leaf_paths(),
+            vec![vec!["items", "[l: 0]", "count"], vec!["geo", "lat"]]
+        );
+
+        let b = json!({"items": [{"price": 1.02, "count": 1}], "geo": {"lat": 1.0, "lon": 2.1}});
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(diff.counts().changed, 2);
+        assert!(similarity(&a, &a, &options) == 1.0);
+    }
+
+    #[test]
+    fn test_numeric_exact() {
+        let exact = DiffOptions::default().numeric_exact(true);
+        let (a, b) = (
+            "[1.0, 1e0, 1.00, 1e2, -0.0, 0.5]",
+            "[1, 1, 1, 100, 0, 5e-1]",
+        );
+        assert!(compare_jsons(a, b, &exact, None).unwrap().is_empty());
+        let diff = compare_jsons(a, b, &DiffOptions::default(), None).unwrap();
+        assert!(!diff.is_empty());
+
+        // integers are not rounded like with a tolerance, which compares as f64
+        let tolerance = DiffOptions::default().tolerances(vec!["**=0".parse().unwrap()]);
+        for (a, b) in [
+            ("[9007199254740993]", "[9007199254740992.0]"),
+            ("[18446744073709551615]", "[18446744073709551614]"),
+        ] {
+            assert!(compare_jsons(a, b, &tolerance, None).unwrap().is_empty());
+            assert!(!compare_jsons(a, b, &exact, None).unwrap().is_empty());
+        }
+        // a tolerance still applies to numbers of different value
+        let both = exact.tolerances(vec!["**=0.1".parse().unwrap()]);
+        assert!(compare_jsons("[1.0]", "[1.05]", &both, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_decimal_normal_form() {
+        assert_eq!(decimal("-1.50e1"), Some((true, "15".to_string(), 0)));
+        assert_eq!(decimal("1200"), Some((false, "12".to_string(), 2)));
+        assert_eq!(decimal("0.0012"), Some((false, "12".to_string(), -4)));
+        assert_eq!(decimal("-0.0e5"), Some((false, String::new(), 0)));
+        assert_eq!(decimal("1E+2"), decimal("100.000"));
+    }
+
+    #[test]
+    fn test_tolerance_modes() {
+        let tolerances = vec!["n=0.001".parse().unwrap()];
+        let absolute = DiffOptions::default().tolerances(tolerances);
+        let relative = absolute.clone().tolerance_mode(ToleranceMode::Relative);
+
+        let (a, b) = (json!({"n": 1_000_000}), json!({"n": 1_000_500}));
+        assert!(!match_json_with_options(&a, &b, &absolute).is_empty());
+        assert!(match_json_with_options(&a, &b, &relative).is_empty());
+
+        let (a, b) = (json!({"n": 0.0}), json!({"n": 0.0000001}));
+        assert!(match_json_with_options(&a, &b, &absolute).is_empty());
+        assert!(!match_json_with_options(&a, &b, &relative).is_empty());
+
+        assert_eq!("relative".parse(), Ok(ToleranceMode::Relative));
+        assert!("percent".parse::<ToleranceMode>().is_err());
+    }
+
+    #[test]
+    fn test_tolerance_parsing() {
+        let tolerance: Tolerance = "a.*.b = 0.5".parse().unwrap();
+        assert_eq!(tolerance.epsilon, 0.5);
+        assert!(tolerance.path.matches(&["a", "x", "b"]));
+        assert!("a.b".parse::<Tolerance>().is_err());
+        assert!("a=-1".parse::<Tolerance>().is_err());
+        assert!("a=x".parse::<Tolerance>().is_err());
+        assert!("=1".parse::<Tolerance>().is_err());
+    }
+
+    #[test]
+    fn test_array_algorithms() {
+        let a = json!([1, 2, 3, {"a": 1}]);
+        let b = json!([0, 1, 2, 3, {"a": 2}]);
+        let lines = |algorithm| {
+            let options = DiffOptions::default().array_algorithm(algorithm);
+            match_json_with_options(&a, &b, &options)
+                .all_diffs()
+                .into_iter()
+                .map(|(d_type, key)| format!("{d_type}: {key}"))
+                .collect::<Vec<_>>()
+        };
+        let expected = vec![
+            "Value changed: [l: 3 r: 4]->a->{1!=2}",
+            "Extra on right: [l: 0]-0",
+        ];
+        assert_eq!(lines(ArrayAlgorithm::Myers), expected);
+        assert_eq!(lines(ArrayAlgorithm::Lcs), expected);
+        assert_eq!(
+            lines(ArrayAlgorithm::Positional),
+            vec![
+                "Value changed: [l: 0]->{1!=0}",
+                "Value changed: [l: 1]->{2!=1}",
+                "Value changed: [l: 2]->{3!=2}",
+                "Type changed: [l: 3]->{{\"a\":1}!=3}",
+                "Extra on right: [l: 4]-{\"a\":2}",
+            ]
+        );
+
+        assert_eq!("lcs".parse(), Ok(ArrayAlgorithm::Lcs));
+        assert!("patience".parse::<ArrayAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn test_detect_moves() {
+        let a = json!({"list": ["a", {"x": 1}, "b", "c"]});
+        let b = json!({"list": ["b", "c", {"x": 1}, "a", "d"]});
+        let options = DiffOptions::default().detect_moves(true);
+        let diff = match_json_with_options(&a, &b, &options);
+        let lines: Vec<String> = diff
+            .all_diffs()
+            .into_iter()
+            .map(|(d_type, key)| format!("{d_type}: {key}"))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Extra on right: list->[l: 4]-\"d\"",
+                "Moved: list->[l: 0]-\"a\" to [l: 3]",
+                "Moved: list->[l: 1]-{\"x\":1} to [l: 2]",
+            ]
+        );
+        assert_eq!(
+            diff.counts().to_string(),
+            "1 added, 0 removed, 0 changed, 2 moved"
+        );
+        assert!(diff.only(&[ChangeKind::Added]).moved.is_empty());
+
+        let single = match_json_with_options(&json!([1, 2, 3]), &json!([2, 3, 1]), &options);
+        assert_eq!(
+            single.moved,
+            vec![Move {
+                path: vec![],
+                from: 0,
+                to: 2,
+                value: json!(1)
+            }]
+        );
+        assert_eq!(single.left_only_keys, KeyNode::Nil);
+        assert_eq!(single.right_only_keys, KeyNode::Nil);
+
+        let plain = match_json(&json!([1, 2, 3]), &json!([2, 3, 1]));
+        assert!(plain.moved.is_empty());
+        assert_eq!(plain.counts().added + plain.counts().removed, 2);
+    }
+
+    #[test]
+    fn test_array_threshold() {
+        let a = json!([1, 2, 3, 4, {"a": 1}, 1.0]);
+        let b = json!([4, 3, 2, 5, {"a": 2}, 1]);
+        let options = DiffOptions::default().array_threshold(Some(5));
+        let lines: Vec<String> = match_json_with_options(&a, &b, &options)
+            .all_diffs()
+            .into_iter()
+            .map(|(d_type, key)| format!("{d_type}: {key}"))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"Extra on left: [l: 4]-{"a":1}"#,
+                "Extra on left: [l: 5]-1.0",
+                "Extra on right: [l: 3]-5",
+                r#"Extra on right: [l: 4]-{"a":2}"#,
+            ]
+        );
+
+        let options = DiffOptions::default().array_threshold(Some(6));
+        assert_ne!(
+            match_json_with_options(&a, &b, &options).counts().changed,
+            0
+        );
+    }
+
+    #[test]
+    fn array_threshold_matches_long_runs_of_equal_values() {
+        // used to pair such a run in quadratic time
+        let a = Value::Array(vec![Value::Null; 200_000]);
+        let mut b = vec![Value::Null; 199_999];
+        b.push(json!(1.0));
+        b.push(json!(1));
+        let options = DiffOptions::default().array_threshold(Some(10));
+        let diff = match_json_with_options(&a, &Value::Array(b), &options);
+        assert_eq!(
+            diff.right_only_keys.leaf_paths(),
+            vec![vec!["[l: 199999]-1.0"], vec!["[l: 200000]-1"]]
+        );
+        assert_eq!(
+            diff.left_only_keys.leaf_paths(),
+            vec![vec!["[l: 199999]-null"]]
+        );
+    }
 
-            fn extract_one_sided_values(
-                v: Vec<(usize, usize)>,
-                vals: &[Value],
-            ) -> Vec<(usize, &Value)> {
-                v.into_iter()
-                    .flat_map(|(o, ol)| (o..o + ol).map(|i| (i, &vals[i])))
-                    .collect::<Vec<(usize, &Value)>>()
-            }
+    #[test]
+    fn test_lcs_finds_longest_common_subsequence() {
+        let a = json!(["a", "b", "c", "d", "e", "f"]);
+        let b = json!(["x", "b", "d", "y", "f", "z"]);
+        let options = DiffOptions::default().array_algorithm(ArrayAlgorithm::Lcs);
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(
+            unchanged_leaves(&a, &b, &options).len(),
+            3,
+            "b, d and f are common"
+        );
+        assert_eq!(diff.counts().changed + diff.counts().removed, 3);
+    }
 
-            let left_only_values: Vec<_> = extract_one_sided_values(deleted, a.as_slice());
-            let right_only_values: Vec<_> = extract_one_sided_values(inserted, b.as_slice());
+    #[test]
+    fn test_count_equal_leaves() {
+        let a =
+            json!({"a": 1, "b": [1, 2, {"c": 3, "d": 4}], "e": {}, "f": 1, "g": {"h": [1, []]}});
+        let b = json!({"a": 1, "b": [2, {"c": 3, "d": 5}], "e": {}, "f": 2, "g": {"h": [1, []]}});
+        let options = DiffOptions::default().count_equal(true);
+        let diff = match_json_with_options(&a, &b, &options);
+        assert_eq!(diff.equal_leaves, 6);
+        assert_eq!(diff.equal_leaves, unchanged_leaves(&a, &b, &options).len());
+        assert_eq!(diff.counts().changed + diff.counts().removed, 3);
 
-            let mut left_only_nodes = values_to_node(left_only_values);
-            let mut right_only_nodes = values_to_node(right_only_values);
-            let mut diff = KeyNode::Nil;
+        assert_eq!(match_json(&a, &b).equal_leaves, 0);
+        let ignored = options.ignore_keys(vec![Regex::new("^x$").unwrap()]);
+        let diff = match_json_with_options(&json!({"x": 1}), &json!({}), &ignored);
+        assert_eq!(diff.equal_leaves, 1);
+    }
 
-            for (o, ol, n, nl) in replaced {
-                let max_length = ol.max(nl);
-                for i in 0..max_length {
-                    let inner_a = a.get(o + i).unwrap_or(&Value::Null);
-                    let inner_b = b.get(n + i).unwrap_or(&Value::Null);
+    #[test]
+    fn test_unchanged_leaves() {
+        let a = json!({"a": 1, "b": [1, 2, {"c": 3, "d": 4}], "e": {}, "f": 1, "g": {"h": 1}});
+        let b = json!({"a": 1, "b": [2, {"c": 3, "d": 5}], "e": {}, "f": 2, "g": {"h": 1}});
+        let paths: Vec<String> = unchanged_leaves(&a, &b, &DiffOptions::default())
+            .iter()
+            .map(|path| path.join("->"))
+            .collect();
+        assert_eq!(paths, vec!["a", "b->[l: 1]", "b->[l: 2]->c", "e", "g->h"]);
 
-                    let cdiff = match_json(inner_a, inner_b, sort_arrays);
-                    let position = o + i;
-                    let Mismatch {
-                        left_only_keys: l,
-                        right_only_keys: r,
-                        keys_in_both: u,
-                    } = cdiff;
-                    left_only_nodes =
-                        insert_child_key_map(left_only_nodes, l, &format!("[l: {position}]"));
-                    right_only_nodes =
-                        insert_child_key_map(right_only_nodes, r, &format!("[l: {position}]"));
-                    diff = insert_child_key_map(diff, u, &format!("[l: {position}]"));
-                }
-            }
+        assert_eq!(
+            unchanged_leaves(&json!(1), &json!(1), &DiffOptions::default()),
+            vec![Vec::<String>::new()]
+        );
+        assert!(unchanged_leaves(&json!(1), &json!(2), &DiffOptions::default()).is_empty());
+    }
 
-            Mismatch::new(left_only_nodes, right_only_nodes, diff)
-        }
-        (a, b) => {
-            if a == b {
-                Mismatch::new(KeyNode::Nil, KeyNode::Nil, KeyNode::Nil)
-            } else {
-                Mismatch::new(
-                    KeyNode::Nil,
-                    KeyNode::Nil,
-                    KeyNode::Value(a.clone(), b.clone()),
-                )
-            }
-        }
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision() {
+        let a = "1234567890.123456789012345678901234567890";
+        let b = "1234567890.123456789012345678901234567891";
+        let diff = compare_jsons(a, b, &DiffOptions::default(), None).unwrap();
+        assert_eq!(diff.counts().changed, 1);
+        assert!(compare_jsons(a, a, &DiffOptions::default(), None)
+            .unwrap()
+            .is_empty());
+
+        let sorted = DiffOptions::default().sort_arrays(true);
+        let diff = compare_jsons(
+            &format!("[{a}, {b}]"),
+            &format!("[{b}, {a}]"),
+            &sorted,
+            None,
+        );
+        assert!(diff.unwrap().is_empty());
+        let diff = compare_jsons(
+            &format!("[{a}, {a}]"),
+            &format!("[{b}, {a}]"),
+            &sorted,
+            None,
+        );
+        assert!(!diff.unwrap().is_empty());
     }
-}
 
-fn preprocess_array(sort_arrays: bool, a: &Vec<Value>) -> Cow<Vec<Value>> {
-    if sort_arrays {
-        let mut owned = a.to_owned();
-        owned.sort_by(compare_values);
-        Cow::Owned(owned)
-    } else {
-        Cow::Borrowed(a)
+    #[test]
+    fn list_diff_handler_cannot_fail() {
+        // array alignment relies on this instead of unwrapping the results of myers and lcs
+        fn assert_infallible<D: Diff<Error = Infallible>>() {}
+        assert_infallible::<ListDiffHandler>();
+        assert_infallible::<Replace<ListDiffHandler>>();
     }
-}
 
-fn compare_values(a: &Value, b: &Value) -> std::cmp::Ordering {
-    match (a, b) {
-        (Value::Null, Value::Null) => std::cmp::Ordering::Equal,
-        (Value::Null, _) => std::cmp::Ordering::Less,
-        (_, Value::Null) => std::cmp::Ordering::Greater,
-        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
-        (Value::Number(a), Value::Number(b)) => {
-            if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
-                return a.cmp(&b);
-            }
-            if let (Some(a), Some(b)) = (a.as_f64(), b.as_f64()) {
-                return a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal);
-            }
-            // Handle other number types if needed
-            std::cmp::Ordering::Equal
-        }
-        (Value::String(a), Value::String(b)) => a.cmp(b),
-        (Value::Array(a), Value::Array(b)) => {
-            for (a, b) in a.iter().zip(b.iter()) {
-                let cmp = compare_values(a, b);
-                if cmp != std::cmp::Ordering::Equal {
-                    return cmp;
+    #[test]
+    fn diff_str_never_panics() {
+        // xorshift, so the inputs are random but the same in every run
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let seed = br#"{"a": [1, 2.5e3, {"b": null}], "c": "d\u00e9", "e": [true, false, []]}"#;
+        let alphabet = br#"{}[]:,"\ 0123456789.eE+-abnutrlsf"#;
+        let options = [
+            DiffOptions::default(),
+            DiffOptions::default()
+                .sort_arrays(true)
+                .detect_moves(true)
+                .parse_embedded_json(true),
+            DiffOptions::default()
+                .array_algorithm(ArrayAlgorithm::Lcs)
+                .keys_only(true)
+                .stop_at_first(true),
+        ];
+        for round in 0..3000 {
+            let mut input = seed.to_vec();
+            if round % 3 == 0 {
+                // random bytes, including invalid UTF-8
+                input = (0..next() % 64).map(|_| next() as u8).collect();
+            } else {
+                for _ in 0..1 + next() % 4 {
+                    let at = (next() as usize) % (input.len() + 1);
+                    let byte = alphabet[(next() as usize) % alphabet.len()];
+                    match next() % 3 {
+                        0 if at < input.len() => input[at] = byte,
+                        1 if at < input.len() => {
+                            input.remove(at);
+                        }
+                        _ => input.insert(at, byte),
+                    }
                 }
             }
-            a.len().cmp(&b.len())
-        }
-        (Value::Object(a), Value::Object(b)) => {
-            let mut keys_a: Vec<_> = a.keys().collect();
-            let mut keys_b: Vec<_> = b.keys().collect();
-            keys_a.sort();
-            keys_b.sort();
-            for (key_a, key_b) in keys_a.iter().zip(keys_b.iter()) {
-                let cmp = key_a.cmp(key_b);
-                if cmp != std::cmp::Ordering::Equal {
-                    return cmp;
-                }
-                let value_a = &a[*key_a];
-                let value_b = &b[*key_b];
-                let cmp = compare_values(value_a, value_b);
-                if cmp != std::cmp::Ordering::Equal {
-                    return cmp;
-                }
+            let input = String::from_utf8_lossy(&input);
+            let other = std::str::from_utf8(seed).unwrap();
+            for options in &options {
+                let _ = diff_str(&input, other, options);
+                let _ = diff_str(other, &input, options);
+                let _ = diff_str(&input, &input, options);
             }
-            keys_a.len().cmp(&keys_b.len())
         }
-        (Value::Object(_), _) => std::cmp::Ordering::Less,
-        (_, Value::Object(_)) => std::cmp::Ordering::Greater,
-        (Value::Bool(_), _) => std::cmp::Ordering::Less,
-        (_, Value::Bool(_)) => std::cmp::Ordering::Greater,
-        (Value::Number(_), _) => std::cmp::Ordering::Less,
-        (_, Value::Number(_)) => std::cmp::Ordering::Greater,
-        (Value::String(_), _) => std::cmp::Ordering::Less,
-        (_, Value::String(_)) => std::cmp::Ordering::Greater,
     }
-}
 
-fn get_map_of_keys(set: Option<HashSet<String>>) -> KeyNode {
-    if let Some(set) = set {
-        KeyNode::Node(
-            set.iter()
-                .map(|key| (String::from(key), KeyNode::Nil))
-                .collect(),
-        )
-    } else {
-        KeyNode::Nil
+    #[test]
+    fn test_common_value() {
+        let a =
+            json!({"a": 1, "b": [1, 2, {"c": 3, "d": 4}], "e": {}, "f": 1, "g": {"h": 1, "t": 1}});
+        let b = json!({"a": 1, "b": [2, {"c": 3, "d": 5}], "e": {}, "f": 2, "g": {"h": 1, "t": 2}});
+        let options = DiffOptions::default().ignore_keys(vec![Regex::new("^t$").unwrap()]);
+        assert_eq!(
+            common_value(&a, &b, &options),
+            Some(json!({"a": 1, "b": [2, {"c": 3}], "e": {}, "g": {"h": 1}}))
+        );
+        assert_eq!(
+            common_value(&json!({"x": [1]}), &json!({"x": [1]}), &options),
+            Some(json!({"x": [1]}))
+        );
+        assert_eq!(
+            common_value(&json!({"a": 1}), &json!({"a": 2}), &options),
+            None
+        );
+        assert_eq!(common_value(&json!(1), &json!("1"), &options), None);
     }
-}
 
-fn insert_child_key_map(parent: KeyNode, child: KeyNode, key: &String) -> KeyNode {
-    if child == KeyNode::Nil {
-        return parent;
-    }
-    if let KeyNode::Node(mut map) = parent {
-        map.insert(String::from(key), child);
-        KeyNode::Node(map) // This is weird! I just wanted to return back `parent` here
-    } else if let KeyNode::Nil = parent {
-        let mut map = HashMap::new();
-        map.insert(String::from(key), child);
-        KeyNode::Node(map)
-    } else {
-        parent // TODO Trying to insert child node in a Value variant : Should not happen => Throw an error instead.
-    }
-}
+    #[test]
+    fn test_root_type_mismatch() {
+        let diff = match_json(&json!({"a": [1, 2]}), &json!([{"a": 1}]));
+        assert_eq!(diff.root_types(), Some((JsonType::Object, JsonType::Array)));
+        assert_eq!(diff.counts().changed, 1);
 
-struct MapDifference {
-    left_only: Option<HashSet<String>>,
-    right_only: Option<HashSet<String>>,
-    intersection: Option<HashSet<String>>,
-}
+        let diff = match_json(&json!([1, 2]), &json!("[1, 2]"));
+        assert_eq!(diff.root_types(), Some((JsonType::Array, JsonType::String)));
 
-impl MapDifference {
-    pub fn new(
-        left_only: Option<HashSet<String>>,
-        right_only: Option<HashSet<String>>,
-        intersection: Option<HashSet<String>>,
-    ) -> Self {
-        Self {
-            right_only,
-            left_only,
-            intersection,
-        }
+        let diff = match_json(&json!({"a": 1}), &json!({"a": [1]}));
+        assert_eq!(diff.root_types(), None);
     }
-}
 
-fn intersect_maps(a: &Map<String, Value>, b: &Map<String, Value>) -> MapDifference {
-    let mut intersection = HashSet::new();
-    let mut left = HashSet::new();
-    let mut right = HashSet::new();
-    for a_key in a.keys() {
-        if b.contains_key(a_key) {
-            intersection.insert(String::from(a_key));
-        } else {
-            left.insert(String::from(a_key));
-        }
-    }
-    for b_key in b.keys() {
-        if !a.contains_key(b_key) {
-            right.insert(String::from(b_key));
-        }
-    }
-    let left = if left.is_empty() { None } else { Some(left) };
-    let right = if right.is_empty() { None } else { Some(right) };
-    let intersection = if intersection.is_empty() {
-        None
-    } else {
-        Some(intersection)
-    };
-    MapDifference::new(left, right, intersection)
-}
+    #[test]
+    fn test_similarity() {
+        let options = DiffOptions::default();
+        let score = |a: Value, b: Value| similarity(&a, &b, &options);
+        assert_eq!(
+            score(
+                json!({"a": [1, {}], "b": null}),
+                json!({"a": [1, {}], "b": null})
+            ),
+            1.0
+        );
+        assert_eq!(score(json!({}), json!({})), 1.0);
+        assert_eq!(score(json!({"a": 1, "b": 2}), json!({"a": 1, "b": 3})), 0.5);
+        assert_eq!(
+            score(json!({"a": 1}), json!({"a": 1, "b": {"c": 1, "d": 2}})),
+            0.5
+        );
+        assert_eq!(score(json!([1, 2, 3]), json!([1, 3])), 0.8);
+        assert_eq!(score(json!({"a": 1}), json!([1])), 0.0);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use maplit::hashmap;
-    use serde_json::json;
+        let options = DiffOptions::default()
+            .null_equals_missing(true)
+            .ignore_keys(vec![Regex::new("^id$").unwrap()]);
+        let a = json!({"a": 1, "b": null, "id": 1});
+        let b = json!({"a": 1, "id": 2});
+        assert_eq!(similarity(&a, &b, &options), 1.0);
+    }
 
     #[test]
-    fn test_arrays_sorted_simple() {
-        let data1 = r#"["a","b","c"]"#;
-        let data2 = r#"["b","c","a"]"#;
-        let diff = compare_jsons(data1, data2, true).unwrap();
-        assert!(diff.is_empty());
+    fn test_source_order_is_kept() {
+        let data1 = r#"{"z": 1, "a": 1, "m": [1, 2, 3, 4], "b": {"y": 1, "x": 1}}"#;
+        let data2 = r#"{"z": 2, "a": 2, "m": [0, 2, 5], "b": {"y": 2, "x": 2}}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let paths: Vec<String> = diff
+            .keys_in_both
+            .leaf_paths()
+            .into_iter()
+            .map(|p| p.join("->"))
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["z", "a", "m->[l: 0]", "m->[l: 2]", "b->y", "b->x"]
+        );
     }
 
     #[test]
-    fn test_arrays_sorted_objects() {
-        let data1 = r#"[{"c": {"d": "e"} },"b","c"]"#;
-        let data2 = r#"["b","c",{"c": {"d": "e"} }]"#;
-        let diff = compare_jsons(data1, data2, true).unwrap();
-        assert!(diff.is_empty());
+    fn test_non_finite_numbers_are_rejected_by_parser() {
+        for data in ["NaN", "Infinity", "-Infinity", "1e400", r#"{"a": NaN}"#] {
+            // numbers out of the f64 range are kept as text with arbitrary precision
+            if cfg!(feature = "arbitrary_precision") && data == "1e400" {
+                continue;
+            }
+            let result = compare_jsons(data, data, &DiffOptions::default(), None);
+            assert!(
+                matches!(
+                    result,
+                    Err(Error::Json {
+                        which: Which::Left,
+                        ..
+                    })
+                ),
+                "{data}"
+            );
+        }
     }
 
     #[test]
-    fn test_arrays_deep_sorted_objects() {
-        let data1 = r#"[{"c": ["d","e"] },"b","c"]"#;
-        let data2 = r#"["b","c",{"c": ["e", "d"] }]"#;
-        let diff = compare_jsons(data1, data2, true).unwrap();
+    fn test_non_finite_values_compare_equal() {
+        let nan = json!({ "a": f64::NAN });
+        let diff = match_json(&nan, &nan.clone());
         assert!(diff.is_empty());
-    }
 
-    #[test]
-    fn test_arrays_deep_sorted_objects_with_outer_diff() {
-        let data1 = r#"[{"c": ["d","e"] },"b"]"#;
-        let data2 = r#"["b","c",{"c": ["e", "d"] }]"#;
-        let diff = compare_jsons(data1, data2, true).unwrap();
-        assert!(!diff.is_empty());
-        let insertions = diff.right_only_keys.absolute_keys_to_vec(None);
-        assert_eq!(insertions.len(), 1);
-        assert_eq!(insertions.first().unwrap().to_string(), r#"[l: 2]-"c""#);
+        let inf = json!([f64::INFINITY]);
+        let neg_inf = json!([f64::NEG_INFINITY]);
+        let diff = match_json(&inf, &neg_inf);
+        assert!(diff.is_empty());
     }
 
     #[test]
-    fn test_arrays_deep_sorted_objects_with_inner_diff() {
-        let data1 = r#"["a",{"c": ["d","e", "f"] },"b"]"#;
-        let data2 = r#"["b",{"c": ["e","d"] },"a"]"#;
-        let diff = compare_jsons(data1, data2, true).unwrap();
-        assert!(!diff.is_empty());
-        let deletions = diff.left_only_keys.absolute_keys_to_vec(None);
-
-        assert_eq!(deletions.len(), 1);
-        assert_eq!(
-            deletions.first().unwrap().to_string(),
-            r#"[l: 0]->c->[l: 2]-"f""#
+    fn test_diff_values_matches_string_comparison() {
+        let (a, b) = (r#"{"a": [1, 2], "b": null}"#, r#"{"a": [2], "c": 1}"#);
+        let options = DiffOptions::default().null_equals_missing(true);
+        let parsed = diff_values(
+            &serde_json::from_str(a).unwrap(),
+            &serde_json::from_str(b).unwrap(),
+            &options,
         );
+        assert_eq!(parsed, compare_jsons(a, b, &options, None).unwrap());
+        assert_eq!(parsed.counts().to_string(), "1 added, 1 removed, 0 changed");
     }
 
     #[test]
-    fn test_arrays_deep_sorted_objects_with_inner_diff_mutation() {
-        let data1 = r#"["a",{"c": ["d", "f"] },"b"]"#;
-        let data2 = r#"["b",{"c": ["e","d"] },"a"]"#;
-        let diff = compare_jsons(data1, data2, true).unwrap();
-        assert!(!diff.is_empty());
+    fn test_nested_array_paths() {
+        let diff = match_json(&json!([[1, 2], [3, 4]]), &json!([[1, 9], [3, 4]]));
         let diffs = diff.keys_in_both.absolute_keys_to_vec(None);
-
         assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].to_string(), "[l: 0]->[l: 1]->{2!=9}");
         assert_eq!(
-            diffs.first().unwrap().to_string(),
-            r#"[l: 0]->c->[l: 1]->{"f"!="e"}"#
+            diff.diffs()[0].path,
+            vec!["[l: 0]".to_string(), "[l: 1]".to_string()]
+        );
+
+        let diff = match_json(&json!([[[1]], [2]]), &json!([[[1, 5]], [2, [6]]]));
+        let lines: Vec<String> = diff
+            .all_diffs()
+            .into_iter()
+            .map(|(d_type, key)| format!("{d_type}: {key}"))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Extra on right: [l: 0]->[l: 0]->[l: 1]-5",
+                "Extra on right: [l: 1]->[l: 1]-[6]",
+            ]
         );
     }
 
@@ -378,7 +2545,7 @@ mod tests {
     fn test_arrays_simple_diff() {
         let data1 = r#"["a","b","c"]"#;
         let data2 = r#"["a","b","d"]"#;
-        let diff = compare_jsons(data1, data2, false).unwrap();
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
         assert_eq!(diff.left_only_keys, KeyNode::Nil);
         assert_eq!(diff.right_only_keys, KeyNode::Nil);
         let diff = diff.keys_in_both.absolute_keys_to_vec(None);
@@ -390,7 +2557,7 @@ mod tests {
     fn test_arrays_more_complex_diff() {
         let data1 = r#"["a","b","c"]"#;
         let data2 = r#"["a","a","b","d"]"#;
-        let diff = compare_jsons(data1, data2, false).unwrap();
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
 
         let changes_diff = diff.keys_in_both.absolute_keys_to_vec(None);
         assert_eq!(diff.left_only_keys, KeyNode::Nil);
@@ -398,7 +2565,7 @@ mod tests {
         assert_eq!(changes_diff.len(), 1);
         assert_eq!(
             changes_diff.first().unwrap().to_string(),
-            r#"[l: 2]->{"c"!="d"}"#
+            r#"[l: 2 r: 3]->{"c"!="d"}"#
         );
         let insertions = diff.right_only_keys.absolute_keys_to_vec(None);
         assert_eq!(insertions.len(), 1);
@@ -409,7 +2576,7 @@ mod tests {
     fn test_arrays_extra_left() {
         let data1 = r#"["a","b","c"]"#;
         let data2 = r#"["a","b"]"#;
-        let diff = compare_jsons(data1, data2, false).unwrap();
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
 
         let diffs = diff.left_only_keys.absolute_keys_to_vec(None);
         assert_eq!(diffs.len(), 1);
@@ -422,7 +2589,7 @@ mod tests {
     fn test_arrays_extra_right() {
         let data1 = r#"["a","b"]"#;
         let data2 = r#"["a","b","c"]"#;
-        let diff = compare_jsons(data1, data2, false).unwrap();
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
 
         let diffs = diff.right_only_keys.absolute_keys_to_vec(None);
         assert_eq!(diffs.len(), 1);
@@ -435,23 +2602,40 @@ mod tests {
     fn long_insertion_modification() {
         let data1 = r#"["a","b","a"]"#;
         let data2 = r#"["a","c","c","c","a"]"#;
-        let diff = compare_jsons(data1, data2, false).unwrap();
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
         let diffs = diff.keys_in_both.absolute_keys_to_vec(None);
 
-        assert_eq!(diffs.len(), 3);
-        let diffs: Vec<_> = diffs.into_iter().map(|d| d.to_string()).collect();
-        assert!(diffs.contains(&r#"[l: 3]->{null!="c"}"#.to_string()));
-        assert!(diffs.contains(&r#"[l: 1]->{"b"!="c"}"#.to_string()));
-        assert!(diffs.contains(&r#"[l: 2]->{"a"!="c"}"#.to_string()));
-        assert_eq!(diff.right_only_keys, KeyNode::Nil);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].to_string(), r#"[l: 1]->{"b"!="c"}"#);
+        let mut insertions: Vec<_> = diff
+            .right_only_keys
+            .absolute_keys_to_vec(None)
+            .into_iter()
+            .map(|d| d.to_string())
+            .collect();
+        insertions.sort();
+        assert_eq!(insertions, vec![r#"[l: 2]-"c""#, r#"[l: 3]-"c""#]);
         assert_eq!(diff.left_only_keys, KeyNode::Nil);
     }
 
+    #[test]
+    fn long_deletion_modification() {
+        let data1 = r#"["a","c","c","c","a"]"#;
+        let data2 = r#"["a","b","a"]"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let diffs = diff.keys_in_both.absolute_keys_to_vec(None);
+
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].to_string(), r#"[l: 1]->{"c"!="b"}"#);
+        assert_eq!(diff.left_only_keys.count_leaves(), 2);
+        assert_eq!(diff.right_only_keys, KeyNode::Nil);
+    }
+
     #[test]
     fn test_arrays_object_extra() {
         let data1 = r#"["a","b"]"#;
         let data2 = r#"["a","b", {"c": {"d": "e"} }]"#;
-        let diff = compare_jsons(data1, data2, false).unwrap();
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
 
         let diffs = diff.right_only_keys.absolute_keys_to_vec(None);
         assert_eq!(diffs.len(), 1);
@@ -463,6 +2647,94 @@ mod tests {
         assert_eq!(diff.left_only_keys, KeyNode::Nil);
     }
 
+    #[test]
+    fn three_way_classifies_changes() {
+        let base = r#"{"a":1,"b":1,"c":1,"d":1,"e":{"f":1}}"#;
+        let mine = r#"{"a":2,"b":1,"c":2,"d":3,"e":{"f":2}}"#;
+        let theirs = r#"{"a":1,"b":2,"c":2,"d":4}"#;
+        let diff = compare_jsons_three_way(base, mine, theirs, &DiffOptions::default()).unwrap();
+        let changes: Vec<_> = diff.changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "ours: a",
+                "theirs: b",
+                "both: c",
+                "conflict: d",
+                "conflict: e",
+                "conflict: e->f"
+            ]
+        );
+        assert!(diff.has_conflicts());
+    }
+
+    #[test]
+    fn three_way_keeps_document_order() {
+        let base = r#"{"z":1,"a":[0,1,2,3,4,5,6,7,8,9,10,11]}"#;
+        let mine = r#"{"z":2,"a":[0,1,20,3,4,5,6,7,8,9,10,11]}"#;
+        let theirs = r#"{"z":1,"a":[0,1,2,3,4,5,6,7,8,9,100,11],"b":1}"#;
+        let diff = compare_jsons_three_way(base, mine, theirs, &DiffOptions::default()).unwrap();
+        let changes: Vec<_> = diff.changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "ours: z",
+                "ours: a->[l: 2]",
+                "theirs: a->[l: 10]",
+                "theirs: b"
+            ]
+        );
+    }
+
+    #[test]
+    fn three_way_filtered() {
+        let base = r#"{"a":1,"b":1}"#;
+        let mine = r#"{"a":2,"b":2}"#;
+        let theirs = r#"{"a":3,"b":1}"#;
+        let (base, mine, theirs) = (
+            serde_json::from_str(base).unwrap(),
+            serde_json::from_str(mine).unwrap(),
+            serde_json::from_str(theirs).unwrap(),
+        );
+        let diff = match_json_three_way_filtered(
+            &base,
+            &mine,
+            &theirs,
+            &DiffOptions::default(),
+            |mismatch| mismatch.retain(|path| path.first().map(String::as_str) != Some("a")),
+        );
+        assert_eq!(
+            diff.changes,
+            vec![ThreeWayChange::new(vec!["b".into()], ChangeOrigin::Ours)]
+        );
+    }
+
+    #[test]
+    fn three_way_parse_errors_name_the_document() {
+        let err = compare_jsons_three_way("{}", "{}", "{", &DiffOptions::default()).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Json {
+                which: Which::Theirs,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn three_way_rejects_empty_documents() {
+        let err = compare_jsons_three_way("{}", " \n", "{}", &DiffOptions::default()).unwrap_err();
+        assert!(matches!(err, Error::EmptyInput(Which::Mine)));
+    }
+
+    #[test]
+    fn three_way_without_changes() {
+        let data = r#"{"a":[1,2,3]}"#;
+        let diff = compare_jsons_three_way(data, data, data, &DiffOptions::default()).unwrap();
+        assert!(diff.is_empty());
+        assert!(!diff.has_conflicts());
+    }
+
     #[test]
     fn nested_diff() {
         let data1 = r#"{
@@ -494,11 +2766,11 @@ mod tests {
             }
         }"#;
 
-        let expected_left = KeyNode::Node(hashmap! {
-        "b".to_string() => KeyNode::Node(hashmap! {
-                "c".to_string() => KeyNode::Node(hashmap! {
+        let expected_left = KeyNode::Node(indexmap! {
+        "b".to_string() => KeyNode::Node(indexmap! {
+                "c".to_string() => KeyNode::Node(indexmap! {
                         "f".to_string() => KeyNode::Nil,
-                        "h".to_string() => KeyNode::Node( hashmap! {
+                        "h".to_string() => KeyNode::Node( indexmap! {
                                 "j".to_string() => KeyNode::Nil,
                             }
                         ),
@@ -506,11 +2778,11 @@ mod tests {
                 ),
             }),
         });
-        let expected_right = KeyNode::Node(hashmap! {
-            "b".to_string() => KeyNode::Node(hashmap! {
-                    "c".to_string() => KeyNode::Node(hashmap! {
+        let expected_right = KeyNode::Node(indexmap! {
+            "b".to_string() => KeyNode::Node(indexmap! {
+                    "c".to_string() => KeyNode::Node(indexmap! {
                             "g".to_string() => KeyNode::Nil,
-                            "h".to_string() => KeyNode::Node(hashmap! {
+                            "h".to_string() => KeyNode::Node(indexmap! {
                                     "k".to_string() => KeyNode::Nil,
                                 }
                             )
@@ -519,11 +2791,11 @@ mod tests {
                 }
             )
         });
-        let expected_uneq = KeyNode::Node(hashmap! {
-            "b".to_string() => KeyNode::Node(hashmap! {
-                    "c".to_string() => KeyNode::Node(hashmap! {
+        let expected_uneq = KeyNode::Node(indexmap! {
+            "b".to_string() => KeyNode::Node(indexmap! {
+                    "c".to_string() => KeyNode::Node(indexmap! {
                             "e".to_string() => KeyNode::Value(json!(5), json!(6)),
-                            "h".to_string() => KeyNode::Node(hashmap! {
+                            "h".to_string() => KeyNode::Node(indexmap! {
                                     "i".to_string() => KeyNode::Value(json!(true), json!(false)),
                                 }
                             )
@@ -534,7 +2806,7 @@ mod tests {
         });
         let expected = Mismatch::new(expected_left, expected_right, expected_uneq);
 
-        let mismatch = compare_jsons(data1, data2, false).unwrap();
+        let mismatch = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
         assert_eq!(mismatch, expected, "Diff was incorrect.");
     }
 
@@ -570,7 +2842,7 @@ mod tests {
         }"#;
 
         assert_eq!(
-            compare_jsons(data1, data2, false).unwrap(),
+            compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap(),
             Mismatch::new(KeyNode::Nil, KeyNode::Nil, KeyNode::Nil)
         );
     }
@@ -581,7 +2853,7 @@ mod tests {
         let data2 = r#"{}"#;
 
         assert_eq!(
-            compare_jsons(data1, data2, false).unwrap(),
+            compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap(),
             Mismatch::new(KeyNode::Nil, KeyNode::Nil, KeyNode::Nil)
         );
     }
@@ -590,22 +2862,52 @@ mod tests {
     fn parse_err_source_one() {
         let invalid_json1 = r#"{invalid: json}"#;
         let valid_json2 = r#"{"a":"b"}"#;
-        match compare_jsons(invalid_json1, valid_json2, false) {
+        match compare_jsons(invalid_json1, valid_json2, &DiffOptions::default(), None) {
             Ok(_) => panic!("This shouldn't be an Ok"),
             Err(err) => {
-                matches!(err, Error::JSON(_));
+                assert!(matches!(
+                    err,
+                    Error::Json {
+                        which: Which::Left,
+                        ..
+                    }
+                ));
+                assert_eq!(
+                    err.to_string(),
+                    "failed to parse source1: key must be a string at line 1 column 2"
+                );
             }
         };
     }
 
+    #[test]
+    fn empty_source_one() {
+        let err = compare_jsons("", r#"{"a":"b"}"#, &DiffOptions::default(), None).unwrap_err();
+        assert!(matches!(err, Error::EmptyInput(Which::Left)));
+        assert_eq!(err.to_string(), "source1 is empty");
+    }
+
+    #[test]
+    fn empty_source_two() {
+        let err = compare_jsons("{}", " \n\t ", &DiffOptions::default(), None).unwrap_err();
+        assert!(matches!(err, Error::EmptyInput(Which::Right)));
+        assert_eq!(err.to_string(), "source2 is empty");
+    }
+
     #[test]
     fn parse_err_source_two() {
         let valid_json1 = r#"{"a":"b"}"#;
         let invalid_json2 = r#"{invalid: json}"#;
-        match compare_jsons(valid_json1, invalid_json2, false) {
+        match compare_jsons(valid_json1, invalid_json2, &DiffOptions::default(), None) {
             Ok(_) => panic!("This shouldn't be an Ok"),
             Err(err) => {
-                matches!(err, Error::JSON(_));
+                assert!(matches!(
+                    err,
+                    Error::Json {
+                        which: Which::Right,
+                        ..
+                    }
+                ));
             }
         };
     }
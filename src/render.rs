@@ -0,0 +1,1337 @@
+use std::str::FromStr;
+
+use colored::{Color, Colorize};
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+
+use crate::ds::key_node::{
+    array_label_index, escape_key, is_array_label, join_path, join_path_with, json_pointer,
+    KeyNode, PATH_SEPARATOR,
+};
+use crate::ds::mismatch::{DiffCounts, DiffEntry, Mismatch, Move};
+use crate::enums::{ChangeKind, DiffType, JsonType, ValueType};
+use crate::parse::SourcePositions;
+
+/// Colors of the text report, one per diff category
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub left_only: Color,
+    pub right_only: Color,
+    pub changed_left: Color,
+    pub changed_right: Color,
+    pub unchanged: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            left_only: Color::Red,
+            right_only: Color::Green,
+            changed_left: Color::Blue,
+            changed_right: Color::Cyan,
+            unchanged: Color::BrightBlack,
+        }
+    }
+}
+
+impl Theme {
+    /// What each color stands for, in the order of the legend
+    pub fn meanings(&self) -> [(&'static str, Color); 5] {
+        [
+            ("only on the left", self.left_only),
+            ("only on the right", self.right_only),
+            ("left value", self.changed_left),
+            ("right value", self.changed_right),
+            ("unchanged", self.unchanged),
+        ]
+    }
+}
+
+/// Parses a comma separated list of `category=color` overrides on top of the default theme,
+/// e.g. `left-only=magenta,changed-right=bright yellow`
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut theme = Theme::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (category, color) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected category=color, got '{entry}'"))?;
+            let color = Color::from_str(color.trim())
+                .map_err(|_| format!("unknown color '{}'", color.trim()))?;
+            match category.trim() {
+                "left-only" => theme.left_only = color,
+                "right-only" => theme.right_only = color,
+                "changed-left" => theme.changed_left = color,
+                "changed-right" => theme.changed_right = color,
+                "unchanged" => theme.unchanged = color,
+                other => return Err(format!("unknown category '{other}'")),
+            }
+        }
+        Ok(theme)
+    }
+}
+
+/// Settings of the text report, the default renders plain lines without colors
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TextOptions<'a> {
+    /// Colors to use, `None` for plain text
+    pub theme: Option<Theme>,
+    /// Pretty print changed objects and arrays below the path
+    pub pretty_values: bool,
+    /// Cut off values longer than this many chars
+    pub max_value_len: Option<usize>,
+    /// Positions in the left and right source, appends ` (line N)` to every entry found there
+    pub positions: Option<(&'a SourcePositions, &'a SourcePositions)>,
+    /// Separator between path segments, `None` for the default `->`
+    pub path_separator: Option<&'a str>,
+    /// Follow both sides of a changed value with their json type, e.g. `5 (number)`
+    pub show_types: bool,
+    /// Pad the paths of changed values so that all `{left!=right}` start in the same column
+    pub align_values: bool,
+    /// Follow changes between two numbers with their difference, e.g. `{3!=8} (+5)`, in the
+    /// color of added keys for increases and of removed keys for decreases
+    pub number_delta: bool,
+}
+
+/// Renders one `<diff type>: <key>` line per difference. If an object or array is compared
+/// against a value of another type at the root, only the two types are named instead of
+/// printing both documents.
+pub fn text(mismatch: &Mismatch, options: &TextOptions) -> Vec<String> {
+    let separator = options.path_separator.unwrap_or(PATH_SEPARATOR);
+    let is_container = |t: JsonType| matches!(t, JsonType::Object | JsonType::Array);
+    if let Some((left, right)) = mismatch.root_types() {
+        if is_container(left) || is_container(right) {
+            return vec![DiffType::RootMismatch { left, right }.to_string()];
+        }
+    }
+    let diffs = mismatch.all_diffs_with(
+        options.max_value_len,
+        options.pretty_values,
+        separator,
+        options.show_types,
+    );
+    // measured without the values, which are the only part that gets colored
+    let path_width = |d_type: &DiffType, key: &str| format!("{d_type}: {key}").chars().count();
+    let column = diffs
+        .iter()
+        .filter(|_| options.align_values)
+        .filter_map(|(d_type, key)| match key {
+            ValueType::Value { key, .. } => Some(path_width(d_type, key)),
+            ValueType::Key(_) => None,
+        })
+        .max();
+    // the changed values in the order of the `ValueType::Value` entries, taken before they are
+    // truncated and formatted
+    let mut changed_values = Vec::new();
+    if options.number_delta {
+        mismatch
+            .keys_in_both
+            .for_each_leaf(&mut |_, values| changed_values.extend(values));
+    }
+    let mut changed_values = changed_values.into_iter();
+    diffs
+        .into_iter()
+        .map(|(d_type, key)| {
+            let line = match &key {
+                ValueType::Value {
+                    key: path,
+                    value_left,
+                    value_right,
+                    ..
+                } => {
+                    let width = column.map_or(0, |column| column - path_width(&d_type, path));
+                    let values = colored_values(value_left, value_right, options.theme.as_ref());
+                    let delta = changed_values
+                        .next()
+                        .and_then(|(left, right)| number_delta(left, right))
+                        .map(|delta| format!(" ({})", colored_delta(delta, options.theme.as_ref())))
+                        .unwrap_or_default();
+                    format!("{d_type}: {path}{:width$}{values}{delta}", "")
+                }
+                ValueType::Key(_) => match &options.theme {
+                    None => format!("{d_type}: {key}"),
+                    Some(theme) => format!("{d_type}: {}", colored_key(&d_type, &key, theme)),
+                },
+            };
+            let source = match (&d_type, options.positions) {
+                (_, None) => None,
+                (DiffType::RightExtra, Some((_, right))) => {
+                    right.get_right_with(key.get_key(), separator)
+                }
+                (_, Some((left, _))) => left.get_with(key.get_key(), separator),
+            };
+            match source {
+                Some((line_number, _)) => format!("{line} (line {line_number})"),
+                None => line,
+            }
+        })
+        .collect()
+}
+
+/// A line naming each color of the theme in that color, printed above a colored report
+pub fn legend(theme: &Theme) -> String {
+    let entries: Vec<String> = theme
+        .meanings()
+        .into_iter()
+        .map(|(meaning, color)| meaning.color(color).to_string())
+        .collect();
+    format!("Legend: {}", entries.join(", "))
+}
+
+/// Renders one `<pointer> = <value>` line per leaf of a single document, with the path as JSON
+/// pointer. Leaves are scalars and empty objects or arrays, a scalar root is listed as `(root)`.
+pub fn flatten(value: &Value) -> Vec<String> {
+    let mut lines = Vec::new();
+    flatten_into(value, &mut Vec::new(), &mut lines);
+    lines
+}
+
+fn flatten_into(value: &Value, path: &mut Vec<String>, lines: &mut Vec<String>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                path.push(key.clone());
+                flatten_into(value, path, lines);
+                path.pop();
+            }
+        }
+        Value::Array(values) if !values.is_empty() => {
+            for (index, value) in values.iter().enumerate() {
+                path.push(index.to_string());
+                flatten_into(value, path, lines);
+                path.pop();
+            }
+        }
+        leaf if path.is_empty() => lines.push(format!("(root) = {leaf}")),
+        leaf => lines.push(format!("{} = {leaf}", json_pointer(path))),
+    }
+}
+
+/// Renders the whole text report without colors into one string: one line per difference as
+/// in `text` followed by the summary line, e.g. `0 added, 1 removed, 0 changed`. Every line
+/// ends with a newline. Nothing is printed, which makes the report easy to assert on.
+pub fn plain(mismatch: &Mismatch) -> String {
+    let mut report = String::new();
+    for line in text(mismatch, &TextOptions::default()) {
+        report.push_str(&line);
+        report.push('\n');
+    }
+    report.push_str(&format!("{}\n", mismatch.counts()));
+    report
+}
+
+/// Renders one `Unchanged: <key>` line per leaf that is equal on both sides, see
+/// `process::unchanged_leaves`
+pub fn unchanged(paths: &[Vec<String>], options: &TextOptions) -> Vec<String> {
+    let separator = options.path_separator.unwrap_or(PATH_SEPARATOR);
+    paths
+        .iter()
+        .map(|path| {
+            let key = if path.is_empty() {
+                "(root)".to_string()
+            } else {
+                join_path_with(path, separator)
+            };
+            let line = match &options.theme {
+                None => format!("Unchanged: {key}"),
+                Some(theme) => format!("Unchanged: {}", key.color(theme.unchanged)),
+            };
+            let source = options
+                .positions
+                .and_then(|(left, _)| left.get_with(&join_path_with(path, separator), separator));
+            match source {
+                Some((line_number, _)) => format!("{line} (line {line_number})"),
+                None => line,
+            }
+        })
+        .collect()
+}
+
+/// Renders one `<key>: +added -removed ~changed` line per top-level key with differences,
+/// like `git diff --stat`, moved array elements are counted as `>N`. Counts of zero are left
+/// out, a change of the root value is listed as `(root)`.
+pub fn stat(mismatch: &Mismatch, options: &TextOptions) -> Vec<String> {
+    let separator = options.path_separator.unwrap_or(PATH_SEPARATOR);
+    let mut groups: IndexMap<String, DiffCounts> = IndexMap::new();
+    for entry in mismatch.diffs() {
+        let key = match entry.path.first() {
+            None => "(root)".to_string(),
+            Some(segment) => match array_label_index(segment) {
+                Some(index) => format!("[l: {index}]"),
+                None => escape_key(segment, separator).into_owned(),
+            },
+        };
+        let counts = groups.entry(key).or_default();
+        match entry.kind {
+            ChangeKind::Added => counts.added += 1,
+            ChangeKind::Removed => counts.removed += 1,
+            ChangeKind::Changed => counts.changed += 1,
+            ChangeKind::Moved => counts.moved += 1,
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(key, counts)| {
+            let parts = [
+                ('+', counts.added, options.theme.map(|t| t.right_only)),
+                ('-', counts.removed, options.theme.map(|t| t.left_only)),
+                ('~', counts.changed, options.theme.map(|t| t.changed_right)),
+                ('>', counts.moved, options.theme.map(|t| t.changed_left)),
+            ];
+            let parts: Vec<String> = parts
+                .into_iter()
+                .filter(|(_, count, _)| *count > 0)
+                .map(|(sign, count, color)| {
+                    let part = format!("{sign}{count}");
+                    match color {
+                        Some(color) => part.color(color).to_string(),
+                        None => part,
+                    }
+                })
+                .collect();
+            format!("{key}: {}", parts.join(" "))
+        })
+        .collect()
+}
+
+/// The `{left!=right}` part of a changed value
+fn colored_values(left: &str, right: &str, theme: Option<&Theme>) -> String {
+    match theme {
+        None => format!("{{{left}!={right}}}"),
+        Some(theme) => format!(
+            "{{{}!={}}}",
+            left.color(theme.changed_left),
+            right.color(theme.changed_right)
+        ),
+    }
+}
+
+/// The difference `right - left` of two numbers as signed text like `+5` or `-0.5`, together
+/// with its sign. Integers are subtracted exactly, other numbers are rounded to the decimals of
+/// the more precise one. `None` unless both values are numbers.
+fn number_delta(left: &Value, right: &Value) -> Option<(String, std::cmp::Ordering)> {
+    let (left, right) = (left.as_number()?, right.as_number()?);
+    let integer = |n: &serde_json::Number| {
+        n.as_i64()
+            .map(i128::from)
+            .or_else(|| n.as_u64().map(i128::from))
+    };
+    match (integer(left), integer(right)) {
+        (Some(left), Some(right)) => Some((format!("{:+}", right - left), right.cmp(&left))),
+        _ => {
+            let delta = right.as_f64()? - left.as_f64()?;
+            let precision = decimals(left).max(decimals(right));
+            Some((format!("{delta:+.precision$}"), delta.partial_cmp(&0.0)?))
+        }
+    }
+}
+
+/// Digits after the decimal point of a number as written, e.g. 2 for `1.25` and 7 for `1e-7`
+fn decimals(number: &serde_json::Number) -> usize {
+    let text = number.to_string().to_ascii_lowercase();
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let fraction = mantissa
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len());
+    let exponent: i64 = exponent.parse().unwrap_or(0);
+    usize::try_from(fraction as i64 - exponent).unwrap_or(0)
+}
+
+fn colored_delta((delta, sign): (String, std::cmp::Ordering), theme: Option<&Theme>) -> String {
+    match (theme, sign) {
+        (Some(theme), std::cmp::Ordering::Greater) => delta.color(theme.right_only).to_string(),
+        (Some(theme), std::cmp::Ordering::Less) => delta.color(theme.left_only).to_string(),
+        _ => delta,
+    }
+}
+
+fn colored_key(d_type: &DiffType, key: &ValueType, theme: &Theme) -> String {
+    match (d_type, key) {
+        (DiffType::LeftExtra, key) => key.to_string().color(theme.left_only).to_string(),
+        (DiffType::RightExtra, key) => key.to_string().color(theme.right_only).to_string(),
+        (_, key) => key.to_string(),
+    }
+}
+
+/// Renders the mismatch as an indented tree with two spaces per level. Objects and arrays
+/// holding differences get a line of their own, changed values are shown as
+/// `key: left != right` and keys only present on one side are prefixed with `-` or `+`.
+/// Moved array elements follow the other lines of their array as `~ [l: 0] -> [l: 3]`.
+pub fn tree(mismatch: &Mismatch, theme: Option<&Theme>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let moves: Vec<&Move> = mismatch.moved.iter().collect();
+    tree_lines(
+        [
+            &mismatch.keys_in_both,
+            &mismatch.left_only_keys,
+            &mismatch.right_only_keys,
+        ],
+        &moves,
+        0,
+        theme,
+        &mut lines,
+    );
+    lines
+}
+
+/// Adds the lines of the changed, left-only and right-only node at the same position and of the
+/// moves within it. `moves` holds the moves below this position, which is `depth` keys deep.
+fn tree_lines(
+    nodes: [&KeyNode; 3],
+    moves: &[&Move],
+    depth: usize,
+    theme: Option<&Theme>,
+    lines: &mut Vec<String>,
+) {
+    let paint = |s: String, color: fn(&Theme) -> Color| match theme {
+        Some(theme) => s.color(color(theme)).to_string(),
+        None => s,
+    };
+    let changed_line = |indent: &str, key: Option<&str>, a: &Value, b: &Value| {
+        let values = format!(
+            "{} != {}",
+            paint(a.to_string(), |t| t.changed_left),
+            paint(b.to_string(), |t| t.changed_right)
+        );
+        match key {
+            Some(key) => format!("{indent}{key}: {values}"),
+            None => format!("{indent}{values}"),
+        }
+    };
+    let indent = "  ".repeat(depth);
+    if let KeyNode::Value(a, b) = nodes[0] {
+        lines.push(changed_line(&indent, None, a, b));
+    }
+    let maps = nodes.map(|node| match node {
+        KeyNode::Node(map) => Some(map),
+        _ => None,
+    });
+    let mut keys: Vec<&String> = Vec::new();
+    let move_keys = moves.iter().filter_map(|m| m.path.get(depth));
+    for key in maps
+        .iter()
+        .flatten()
+        .flat_map(|map| map.keys())
+        .chain(move_keys)
+    {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    if keys.iter().all(|key| is_array_label(key)) {
+        keys.sort_by_key(|key| array_label_index(key));
+    }
+    for key in keys {
+        let children = maps.map(|map| map.and_then(|map| map.get(key)).unwrap_or(&KeyNode::Nil));
+        if let KeyNode::Value(a, b) = children[0] {
+            lines.push(changed_line(&indent, Some(key), a, b));
+        }
+        // a one-sided key is a `Nil` leaf in the left-only or right-only map
+        if maps[1].is_some_and(|map| map.get(key) == Some(&KeyNode::Nil)) {
+            lines.push(paint(format!("{indent}- {key}"), |t| t.left_only));
+        }
+        if maps[2].is_some_and(|map| map.get(key) == Some(&KeyNode::Nil)) {
+            lines.push(paint(format!("{indent}+ {key}"), |t| t.right_only));
+        }
+        let nested_moves: Vec<&Move> = moves
+            .iter()
+            .filter(|m| m.path.get(depth) == Some(key))
+            .copied()
+            .collect();
+        if !nested_moves.is_empty()
+            || children
+                .iter()
+                .any(|child| matches!(child, KeyNode::Node(_)))
+        {
+            lines.push(format!("{indent}{key}"));
+            let nested = children.map(|child| match child {
+                KeyNode::Node(_) => child,
+                _ => &KeyNode::Nil,
+            });
+            tree_lines(nested, &nested_moves, depth + 1, theme, lines);
+        }
+    }
+    for moved in moves.iter().filter(|m| m.path.len() == depth) {
+        let line = format!("{indent}~ [l: {}] -> [l: {}]", moved.from, moved.to);
+        lines.push(paint(line, |t| t.changed_left));
+    }
+}
+
+/// Renders every difference as its path followed by the left and the right value in two
+/// columns, so that the whole line fits into `width` chars. Long values wrap inside their
+/// column, a side without the key shows `(missing)`.
+pub fn side_by_side(mismatch: &Mismatch, width: usize) -> Vec<String> {
+    let column = (width.saturating_sub(5) / 2).max(10);
+    let wrap = |text: String| -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        chars
+            .chunks(column)
+            .map(|chunk| chunk.iter().collect())
+            .collect()
+    };
+    let mut lines = Vec::new();
+    for entry in mismatch.diffs() {
+        let side = |value: Option<&Value>, present: bool| match (value, present) {
+            (Some(value), _) => value.to_string(),
+            (None, true) => "(present)".to_string(),
+            (None, false) => "(missing)".to_string(),
+        };
+        let left = side(entry.left.as_ref(), entry.kind != ChangeKind::Added);
+        let right = side(entry.right.as_ref(), entry.kind != ChangeKind::Removed);
+        lines.push(entry_path(&entry));
+        let (left, right) = (wrap(left), wrap(right));
+        for row in 0..left.len().max(right.len()) {
+            let left = left.get(row).map(String::as_str).unwrap_or_default();
+            let right = right.get(row).map(String::as_str).unwrap_or_default();
+            let line = format!("  {left:<column$} | {right}");
+            lines.push(line.trim_end().to_string());
+        }
+    }
+    lines
+}
+
+/// Writes one json object per difference and line: `{"path":"/a/0","change":"changed",
+/// "left":1,"right":2}`, with the path as JSON pointer. `left` and `right` are left out where
+/// the value is not known, i.e. for added and removed object keys. Moved array elements carry
+/// their new position as pointer in `to`. Lines are written while walking the mismatch, nothing
+/// is collected beforehand, and `output` is flushed after the last one, so that a reader of a
+/// file or pipe sees the differences of each mismatch as soon as it is reported.
+pub fn jsonl(mismatch: &Mismatch, output: &mut impl std::io::Write) -> std::io::Result<()> {
+    jsonl_with(mismatch, &serde_json::Map::new(), output)
+}
+
+/// Like [`jsonl`], but every object starts with the given `fields`, e.g. the names of the
+/// compared files when the lines of several comparisons end up in one place
+pub fn jsonl_with(
+    mismatch: &Mismatch,
+    fields: &serde_json::Map<String, Value>,
+    output: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let mut result = Ok(());
+    mismatch.for_each_diff(&mut |leaf| {
+        if result.is_err() {
+            return;
+        }
+        let mut line = fields.clone();
+        line.insert("path".into(), json_pointer(leaf.path).into());
+        line.insert("change".into(), leaf.kind.to_string().into());
+        if let (Some(to), Some((_, array))) = (leaf.moved_to, leaf.path.split_last()) {
+            let mut to_path = array.to_vec();
+            to_path.push(format!("[l: {to}]"));
+            line.insert("to".into(), json_pointer(&to_path).into());
+        }
+        if let Some(left) = leaf.left {
+            line.insert("left".into(), left.into_owned());
+        }
+        if let Some(right) = leaf.right {
+            line.insert("right".into(), right.into_owned());
+        }
+        result = writeln!(output, "{}", Value::Object(line));
+    });
+    result.and_then(|_| output.flush())
+}
+
+/// The aggregate numbers of a comparison as one json object: `{"added":3,"removed":1,
+/// "changed":2,"moved":0,"equal":57,"similarity":0.89}`. `equal` is `Mismatch::equal_leaves`,
+/// which is only counted with `DiffOptions::count_equal`, and `similarity` the score of
+/// `Mismatch::similarity`, rounded to four decimals.
+pub fn summary(mismatch: &Mismatch) -> Value {
+    let counts = mismatch.counts();
+    json!({
+        "added": counts.added,
+        "removed": counts.removed,
+        "changed": counts.changed,
+        "moved": counts.moved,
+        "equal": mismatch.equal_leaves,
+        "similarity": (mismatch.similarity() * 10_000.0).round() / 10_000.0,
+    })
+}
+
+/// Renders the mismatch in the `-`/`+` line style known from `git diff`.
+/// Changed values become a `-` line with the left and a `+` line with the right value,
+/// keys only present on one side become a single `-` or `+` line. A moved array element is
+/// removed at its old and added at its new index. With a theme, `-` lines are painted in the
+/// left-only and `+` lines in the right-only color.
+pub fn unified(mismatch: &Mismatch, theme: Option<&Theme>) -> Vec<String> {
+    let mut lines = Vec::new();
+    mismatch.keys_in_both.for_each_leaf(&mut |path, values| {
+        if let Some((left, right)) = values {
+            lines.push(unified_line('-', path, Some(&left.to_string()), theme));
+            lines.push(unified_line('+', path, Some(&right.to_string()), theme));
+        }
+    });
+    mismatch
+        .left_only_keys
+        .for_each_leaf(&mut |path, _| lines.push(unified_line('-', path, None, theme)));
+    mismatch
+        .right_only_keys
+        .for_each_leaf(&mut |path, _| lines.push(unified_line('+', path, None, theme)));
+    for moved in &mismatch.moved {
+        let value = moved.value.to_string();
+        let mut to = moved.path.clone();
+        to.push(format!("[l: {}]", moved.to));
+        lines.push(unified_line(
+            '-',
+            &moved.element_path(),
+            Some(&value),
+            theme,
+        ));
+        lines.push(unified_line('+', &to, Some(&value), theme));
+    }
+    lines
+}
+
+fn unified_line(sign: char, path: &[String], value: Option<&str>, theme: Option<&Theme>) -> String {
+    // a one-sided array element carries its value in the label, it is shown like a changed one
+    let element = match (value, path.split_last()) {
+        (None, Some((last, parent))) => split_element_label(last).map(|(label, value)| {
+            let mut path = parent.to_vec();
+            path.push(label.to_string());
+            (path, value)
+        }),
+        _ => None,
+    };
+    let (path, value) = match &element {
+        Some((path, value)) => (join_path(path), Some(*value)),
+        None => (join_path(path), value),
+    };
+    let line = match value {
+        Some(value) if path.is_empty() => format!("{sign} {value}"),
+        Some(value) => format!("{sign} {path}: {value}"),
+        None => format!("{sign} {path}"),
+    };
+    match theme {
+        Some(theme) if sign == '-' => line.color(theme.left_only).to_string(),
+        Some(theme) => line.color(theme.right_only).to_string(),
+        None => line,
+    }
+}
+
+/// Splits the label of a one-sided array element, `[l: 0]-<value>`, into `[l: 0]` and the value
+fn split_element_label(segment: &str) -> Option<(&str, &str)> {
+    array_label_index(segment)?;
+    let end = segment.find("]-")? + 1;
+    Some((&segment[..end], &segment[end + 1..]))
+}
+
+/// Renders the mismatch as a self-contained HTML page with one table row per difference.
+/// Rows carry the change kind as class so they can be colored without external stylesheets.
+pub fn html(mismatch: &Mismatch) -> String {
+    let mut page = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>json diff</title>\n\
+         <style>\n\
+         table { border-collapse: collapse; font-family: monospace; }\n\
+         th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+         tr.changed { background: #e8f0fe; }\n\
+         tr.removed { background: #fde8e8; }\n\
+         tr.added { background: #e6f4ea; }\n\
+         </style>\n</head>\n<body>\n<table>\n\
+         <tr><th>Path</th><th>Left</th><th>Right</th><th>Change</th></tr>\n",
+    );
+    for entry in mismatch.diffs() {
+        page.push_str(&format!(
+            "<tr class=\"{kind}\"><td>{}</td><td>{}</td><td>{}</td><td>{kind}</td></tr>\n",
+            escape_html(&entry_path(&entry)),
+            escape_html(&value_text(entry.left.as_ref())),
+            escape_html(&value_text(entry.right.as_ref())),
+            kind = entry.kind,
+        ));
+    }
+    page.push_str("</table>\n</body>\n</html>\n");
+    page
+}
+
+/// Renders the mismatch as GitHub-flavored Markdown, one table per kind of change.
+/// Kinds without differences are left out, an empty mismatch renders to an empty string.
+pub fn markdown(mismatch: &Mismatch) -> String {
+    let entries = mismatch.diffs();
+    let mut doc = String::new();
+    for (kind, title) in [
+        (ChangeKind::Changed, "Changed"),
+        (ChangeKind::Removed, "Removed"),
+        (ChangeKind::Added, "Added"),
+        (ChangeKind::Moved, "Moved"),
+    ] {
+        let mut rows = entries.iter().filter(|e| e.kind == kind).peekable();
+        if rows.peek().is_none() {
+            continue;
+        }
+        if !doc.is_empty() {
+            doc.push('\n');
+        }
+        doc.push_str(&format!(
+            "### {title}\n\n| Path | Left | Right | Change |\n| --- | --- | --- | --- |\n"
+        ));
+        for entry in rows {
+            doc.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                escape_markdown(&entry_path(entry)),
+                escape_markdown(&value_text(entry.left.as_ref())),
+                escape_markdown(&value_text(entry.right.as_ref())),
+                entry.kind,
+            ));
+        }
+    }
+    doc
+}
+
+/// Path of an entry, followed by ` to [l: <index>]` for a moved element
+fn entry_path(entry: &DiffEntry) -> String {
+    match entry.moved_to {
+        Some(to) => format!("{} to [l: {to}]", join_path(&entry.path)),
+        None => join_path(&entry.path),
+    }
+}
+
+fn escape_markdown(s: &str) -> String {
+    s.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+fn value_text(value: Option<&Value>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::DiffOptions;
+    use crate::process::{compare_jsons, match_json, match_json_with_options};
+    use serde_json::json;
+
+    #[test]
+    fn theme_overrides_defaults() {
+        let theme: Theme = "left-only=magenta, changed-right=bright yellow"
+            .parse()
+            .unwrap();
+        assert_eq!(theme.left_only, Color::Magenta);
+        assert_eq!(theme.right_only, Color::Green);
+        assert_eq!(theme.changed_left, Color::Blue);
+        assert_eq!(theme.changed_right, Color::BrightYellow);
+        assert_eq!("".parse::<Theme>().unwrap(), Theme::default());
+        assert!("left-only=plaid".parse::<Theme>().is_err());
+        assert!("middle=red".parse::<Theme>().is_err());
+        assert!("red".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn text_lines_are_colored_by_theme() {
+        colored::control::set_override(true);
+        let data1 = r#"{"a": 1, "b": 1}"#;
+        let data2 = r#"{"a": 2, "c": 1}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let theme = Theme {
+            left_only: Color::Yellow,
+            ..Theme::default()
+        };
+        assert_eq!(
+            text(
+                &diff,
+                &TextOptions {
+                    theme: Some(theme),
+                    ..TextOptions::default()
+                }
+            ),
+            vec![
+                "Value changed: a->{\u{1b}[34m1\u{1b}[0m!=\u{1b}[36m2\u{1b}[0m}",
+                "Extra on left: \u{1b}[33mb\u{1b}[0m",
+                "Extra on right: \u{1b}[32mc\u{1b}[0m",
+            ]
+        );
+        assert_eq!(
+            text(&diff, &TextOptions::default()),
+            vec![
+                "Value changed: a->{1!=2}",
+                "Extra on left: b",
+                "Extra on right: c"
+            ]
+        );
+    }
+
+    #[test]
+    fn text_number_deltas() {
+        colored::control::set_override(true);
+        let data1 = r#"{"a": 3, "b": 2.5, "c": "x", "d": 1, "e": 18446744073709551615}"#;
+        let data2 = r#"{"a": 8, "b": 2, "c": "y", "d": "1", "e": 0}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let options = TextOptions {
+            number_delta: true,
+            show_types: true,
+            ..TextOptions::default()
+        };
+        assert_eq!(
+            text(&diff, &options),
+            vec![
+                "Value changed: a->{3 (number)!=8 (number)} (+5)",
+                "Value changed: b->{2.5 (number)!=2 (number)} (-0.5)",
+                "Value changed: c->{\"x\" (string)!=\"y\" (string)}",
+                "Type changed: d->{1 (number)!=\"1\" (string)}",
+                "Value changed: e->{18446744073709551615 (number)!=0 (number)} (-18446744073709551615)",
+            ]
+        );
+        let colored = text(
+            &diff,
+            &TextOptions {
+                theme: Some(Theme::default()),
+                ..options
+            },
+        );
+        assert!(colored[0].ends_with(" (\u{1b}[32m+5\u{1b}[0m)"));
+        assert!(colored[1].ends_with(" (\u{1b}[31m-0.5\u{1b}[0m)"));
+    }
+
+    #[test]
+    fn text_number_deltas_of_decimals_and_truncated_values() {
+        let data1 = r#"{"a": 0.1, "b": 1e-7, "c": 1234567, "d": 1.5}"#;
+        let data2 = r#"{"a": 0.3, "b": 3e-7, "c": 1234568, "d": 2}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let options = TextOptions {
+            number_delta: true,
+            align_values: false,
+            ..TextOptions::default()
+        };
+        assert_eq!(
+            text(&diff, &options),
+            vec![
+                "Value changed: a->{0.1!=0.3} (+0.2)",
+                "Value changed: b->{1e-7!=3e-7} (+0.0000002)",
+                "Value changed: c->{1234567!=1234568} (+1)",
+                "Value changed: d->{1.5!=2} (+0.5)",
+            ]
+        );
+        let truncated = TextOptions {
+            max_value_len: Some(3),
+            ..options
+        };
+        let lines = text(&diff, &truncated);
+        assert!(lines[2].ends_with(" (+1)"), "{}", lines[2]);
+    }
+
+    #[test]
+    fn text_values_are_aligned() {
+        colored::control::set_override(true);
+        let data1 = r#"{"a": 1, "bcd": {"e": "x"}, "f": 1}"#;
+        let data2 = r#"{"a": 2, "bcd": {"e": 1}}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let options = TextOptions {
+            align_values: true,
+            ..TextOptions::default()
+        };
+        assert_eq!(
+            text(&diff, &options),
+            vec![
+                "Value changed: a->    {1!=2}",
+                "Type changed: bcd->e->{\"x\"!=1}",
+                "Extra on left: f",
+            ]
+        );
+        // the escape codes of colored values don't count towards the width
+        let colored = text(
+            &diff,
+            &TextOptions {
+                theme: Some(Theme::default()),
+                ..options
+            },
+        );
+        assert!(colored[0].starts_with("Value changed: a->    {\u{1b}"));
+    }
+
+    #[test]
+    fn legend_uses_theme_colors() {
+        colored::control::set_override(true);
+        let theme = Theme {
+            changed_left: Color::Magenta,
+            ..Theme::default()
+        };
+        assert_eq!(
+            legend(&theme),
+            "Legend: \u{1b}[31monly on the left\u{1b}[0m, \u{1b}[32monly on the right\u{1b}[0m, \
+             \u{1b}[35mleft value\u{1b}[0m, \u{1b}[36mright value\u{1b}[0m, \u{1b}[90munchanged\u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn library_paths_stay_uncolored() {
+        colored::control::set_override(true);
+        let data1 = r#"{"a": 1, "b": [1], "c": {"d": 1}}"#;
+        let data2 = r#"{"a": 2, "b": [1, 2], "e": 1}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let mut plain: Vec<String> = diff
+            .all_diffs()
+            .iter()
+            .map(|(d_type, key)| format!("{d_type}: {key}"))
+            .collect();
+        plain.extend(diff.diffs().iter().flat_map(|e| e.path.clone()));
+        plain.extend(unified(&diff, None));
+        assert!(!plain.is_empty());
+        assert!(plain.iter().all(|line| !line.contains('\u{1b}')));
+
+        let colored = text(
+            &diff,
+            &TextOptions {
+                theme: Some(Theme::default()),
+                ..TextOptions::default()
+            },
+        );
+        assert!(colored.iter().all(|line| line.contains('\u{1b}')));
+    }
+
+    #[test]
+    fn text_pretty_values() {
+        let data1 = r#"{"a": {"b": [1]}, "c": 1}"#;
+        let data2 = r#"{"a": {"b": {"x": 1}}, "c": 2}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let mut lines = text(
+            &diff,
+            &TextOptions {
+                pretty_values: true,
+                ..TextOptions::default()
+            },
+        );
+        lines.sort();
+        assert_eq!(
+            lines,
+            vec![
+                "Type changed: a->b->{\n    [\n      1\n    ]\n!=\n    {\n      \"x\": 1\n    }\n}",
+                "Value changed: c->{1!=2}",
+            ]
+        );
+    }
+
+    #[test]
+    fn text_truncates_long_values() {
+        let data1 = r#"{"a": "abcdefghij", "b": "shor"}"#;
+        let data2 = r#"{"a": "abc", "b": "0123456789"}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let mut lines = text(
+            &diff,
+            &TextOptions {
+                max_value_len: Some(6),
+                ..TextOptions::default()
+            },
+        );
+        lines.sort();
+        assert_eq!(
+            lines,
+            vec![
+                "Value changed: a->{\"abcde…(truncated)!=\"abc\"}",
+                "Value changed: b->{\"shor\"!=\"01234…(truncated)}",
+            ]
+        );
+    }
+
+    #[test]
+    fn text_line_numbers() {
+        let data1 = "{\n  \"a\": 1,\n  \"b\": [1, 2]\n}";
+        let data2 = "{\"a\": 2, \"b\": [1],\n \"c\": 1}";
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let (left, right) = (SourcePositions::of(data1), SourcePositions::of(data2));
+        let lines = text(
+            &diff,
+            &TextOptions {
+                positions: Some((&left, &right)),
+                ..TextOptions::default()
+            },
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "Value changed: a->{1!=2} (line 2)",
+                "Extra on left: b->[l: 1]-2 (line 3)",
+                "Extra on right: c (line 2)",
+            ]
+        );
+    }
+
+    #[test]
+    fn text_custom_path_separator() {
+        let data1 = "{\"a\": {\"b.c\": 1, \"d\": [1]}}";
+        let data2 = "{\"a\": {\"b.c\": 2,\n \"d\": [1, 2]}}";
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let (left, right) = (SourcePositions::of(data1), SourcePositions::of(data2));
+        let lines = text(
+            &diff,
+            &TextOptions {
+                positions: Some((&left, &right)),
+                path_separator: Some("."),
+                ..TextOptions::default()
+            },
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "Value changed: a.\"b.c\".{1!=2} (line 1)",
+                "Extra on right: a.d.[l: 1]-2 (line 2)",
+            ]
+        );
+    }
+
+    #[test]
+    fn html_rows_are_escaped() {
+        let data1 = r#"{"<a>": "x & y", "b": 1}"#;
+        let data2 = r#"{"<a>": "</td>", "c": 1}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let page = html(&diff);
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains(
+            "<tr class=\"changed\"><td>&lt;a&gt;</td><td>&quot;x &amp; y&quot;</td>\
+             <td>&quot;&lt;/td&gt;&quot;</td><td>changed</td></tr>"
+        ));
+        assert!(page
+            .contains("<tr class=\"removed\"><td>b</td><td></td><td></td><td>removed</td></tr>"));
+        assert!(
+            page.contains("<tr class=\"added\"><td>c</td><td></td><td></td><td>added</td></tr>")
+        );
+        assert!(page.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn markdown_tables_per_kind() {
+        let data1 = r#"{"a|b": "x|y", "c": 1}"#;
+        let data2 = r#"{"a|b": "z", "d\ne": 1}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        assert_eq!(
+            markdown(&diff),
+            "### Changed\n\n\
+             | Path | Left | Right | Change |\n\
+             | --- | --- | --- | --- |\n\
+             | a\\|b | \"x\\|y\" | \"z\" | changed |\n\
+             \n### Removed\n\n\
+             | Path | Left | Right | Change |\n\
+             | --- | --- | --- | --- |\n\
+             | c |  |  | removed |\n\
+             \n### Added\n\n\
+             | Path | Left | Right | Change |\n\
+             | --- | --- | --- | --- |\n\
+             | d<br>e |  |  | added |\n"
+        );
+    }
+
+    #[test]
+    fn markdown_omits_empty_kinds() {
+        let diff = compare_jsons(
+            r#"{"a": 1}"#,
+            r#"{"a": 1, "b": 2}"#,
+            &DiffOptions::default(),
+            None,
+        )
+        .unwrap();
+        let doc = markdown(&diff);
+        assert!(doc.starts_with("### Added"));
+        assert!(!doc.contains("### Changed"));
+        assert!(!doc.contains("### Removed"));
+        assert!(markdown(&Mismatch::empty()).is_empty());
+    }
+
+    #[test]
+    fn tree_nests_all_kinds() {
+        let data1 = r#"{"a": {"b": 1, "c": [1, 2, 3], "d": true}, "e": 1, "f": 1}"#;
+        let data2 = r#"{"a": {"b": 2, "c": [1, 3], "x": null}, "e": 1, "g": {"h": 1}}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        assert_eq!(
+            tree(&diff, None),
+            vec![
+                "a",
+                "  b: 1 != 2",
+                "  - d",
+                "  c",
+                "    - [l: 1]-2",
+                "  + x",
+                "- f",
+                "+ g",
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_moved_elements() {
+        let options = DiffOptions::default().detect_moves(true);
+        let diff = match_json_with_options(
+            &json!({"a": {"l": [1, 2, 3]}, "b": 1}),
+            &json!({"a": {"l": [2, 3, 1]}, "b": 2}),
+            &options,
+        );
+        assert_eq!(
+            tree(&diff, None),
+            vec!["b: 1 != 2", "a", "  l", "    ~ [l: 0] -> [l: 2]"]
+        );
+        let diff = match_json_with_options(&json!([1, 2]), &json!([2, 1]), &options);
+        assert_eq!(tree(&diff, None), vec!["~ [l: 0] -> [l: 1]"]);
+    }
+
+    #[test]
+    fn tree_root_value_and_colors() {
+        let diff = compare_jsons("1", "2", &DiffOptions::default(), None).unwrap();
+        assert_eq!(tree(&diff, None), vec!["1 != 2"]);
+
+        colored::control::set_override(true);
+        let diff =
+            compare_jsons(r#"{"a": 1}"#, r#"{"b": 1}"#, &DiffOptions::default(), None).unwrap();
+        let lines = tree(&diff, Some(&Theme::default()));
+        assert_eq!(
+            lines,
+            vec!["\u{1b}[31m- a\u{1b}[0m", "\u{1b}[32m+ b\u{1b}[0m"]
+        );
+    }
+
+    #[test]
+    fn side_by_side_columns() {
+        let data1 = r#"{"a": "abcdefghijklmnop", "b": 1, "c": [1]}"#;
+        let data2 = r#"{"a": "x", "d": 2, "c": []}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        assert_eq!(
+            side_by_side(&diff, 25),
+            vec![
+                "a",
+                r#"  "abcdefghi | "x""#,
+                r#"  jklmnop"   |"#,
+                "b",
+                "  (present)  | (missing)",
+                "c->[l: 0]-1",
+                "  1          | (missing)",
+                "d",
+                "  (missing)  | (present)",
+            ]
+        );
+    }
+
+    #[test]
+    fn flatten_lists_leaves() {
+        let value = json!({"a": {"b": [1, {"c~/d": "x"}]}, "e": {}, "f": [], "g": null});
+        assert_eq!(
+            flatten(&value),
+            vec![
+                "/a/b/0 = 1",
+                r#"/a/b/1/c~0~1d = "x""#,
+                "/e = {}",
+                "/f = []",
+                "/g = null"
+            ]
+        );
+        assert_eq!(flatten(&json!("s")), vec![r#"(root) = "s""#]);
+        assert_eq!(flatten(&json!({})), vec!["(root) = {}"]);
+    }
+
+    #[test]
+    fn jsonl_lines_with_pointers() {
+        let data1 = r#"{"a/b": {"c~d": 1}, "list": [1, {"x": 2}], "gone": {"y": 1}}"#;
+        let data2 = r#"{"a/b": {"c~d": 2}, "list": [{"x": 3}], "new": null}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        let mut output = Vec::new();
+        jsonl(&diff, &mut output).unwrap();
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                json!({"path": "/a~1b/c~0d", "change": "changed", "left": 1, "right": 2}),
+                json!({"path": "/list/0", "change": "changed", "left": 1, "right": {"x": 3}}),
+                json!({"path": "/gone", "change": "removed"}),
+                json!({"path": "/list/1", "change": "removed", "left": {"x": 2}}),
+                json!({"path": "/new", "change": "added"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_counts() {
+        let (a, b) = (
+            json!({"a": 1, "b": 2, "c": [1]}),
+            json!({"a": 1, "b": 3, "d": 1}),
+        );
+        let options = DiffOptions::default().count_equal(true);
+        let diff = crate::process::match_json_with_options(&a, &b, &options);
+        assert_eq!(
+            serde_json::to_string(&summary(&diff)).unwrap(),
+            r#"{"added":1,"removed":1,"changed":1,"moved":0,"equal":1,"similarity":0.3333}"#
+        );
+        let diff = diff.retain(|path| path != ["b"]);
+        assert_eq!(
+            serde_json::to_string(&summary(&diff)).unwrap(),
+            r#"{"added":1,"removed":1,"changed":0,"moved":0,"equal":1,"similarity":0.5}"#
+        );
+    }
+
+    #[test]
+    fn unchanged_lines() {
+        let paths = vec![
+            vec!["a".to_string(), "[l: 0]".to_string()],
+            vec!["x.y".to_string()],
+            vec![],
+        ];
+        let options = TextOptions {
+            path_separator: Some("."),
+            ..TextOptions::default()
+        };
+        assert_eq!(
+            unchanged(&paths, &options),
+            vec![
+                "Unchanged: a.[l: 0]",
+                r#"Unchanged: "x.y""#,
+                "Unchanged: (root)"
+            ]
+        );
+    }
+
+    #[test]
+    fn root_type_mismatch_names_only_the_types() {
+        let lines = |a, b| text(&match_json(&a, &b), &TextOptions::default());
+        assert_eq!(
+            lines(json!({"a": 1}), json!([1, 2])),
+            vec!["root: object != array"]
+        );
+        assert_eq!(lines(json!([1]), json!(3)), vec!["root: array != number"]);
+        assert_eq!(
+            lines(json!(1), json!("1")),
+            vec![r#"root: number != string: {1!="1"}"#]
+        );
+    }
+
+    #[test]
+    fn plain_report() {
+        let diff = match_json(
+            &json!({"a": 1, "b": {"c": [1, 2]}, "d": true}),
+            &json!({"a": "1", "b": {"c": [1]}, "e": null}),
+        );
+        assert_eq!(
+            plain(&diff),
+            "Type changed: a->{1!=\"1\"}\n\
+             Extra on left: d\n\
+             Extra on left: b->c->[l: 1]-2\n\
+             Extra on right: e\n\
+             1 added, 2 removed, 1 changed\n"
+        );
+        assert_eq!(plain(&Mismatch::empty()), "0 added, 0 removed, 0 changed\n");
+    }
+
+    #[test]
+    fn value_types() {
+        let diff = match_json(
+            &json!({"a": 5, "b": [true]}),
+            &json!({"a": "5", "b": [null]}),
+        );
+        let options = TextOptions {
+            show_types: true,
+            ..TextOptions::default()
+        };
+        assert_eq!(
+            text(&diff, &options),
+            vec![
+                r#"Type changed: a->{5 (number)!="5" (string)}"#,
+                "Type changed: b->[l: 0]->{true (bool)!=null (null)}"
+            ]
+        );
+    }
+
+    #[test]
+    fn stat_lines() {
+        let a = json!({"users": [{"name": "a"}, {"name": "b"}], "config": {"x": 1}, "old": 1});
+        let b = json!({"users": [{"name": "c"}, {"name": "b"}, 3, 4], "config": {"x": 2}});
+        let mismatch = match_json(&a, &b);
+        assert_eq!(
+            stat(&mismatch, &TextOptions::default()),
+            vec!["users: +2 ~1", "config: ~1", "old: -1"]
+        );
+
+        let mismatch = match_json(&json!(1), &json!("1"));
+        assert_eq!(stat(&mismatch, &TextOptions::default()), vec!["(root): ~1"]);
+    }
+
+    #[test]
+    fn unified_lines() {
+        let data1 = r#"{"a": {"b": 1}, "c": 1}"#;
+        let data2 = r#"{"a": {"b": "2"}, "d": 1}"#;
+        let diff = compare_jsons(data1, data2, &DiffOptions::default(), None).unwrap();
+        assert_eq!(
+            unified(&diff, None),
+            vec!["- a->b: 1", "+ a->b: \"2\"", "- c", "+ d"]
+        );
+    }
+
+    #[test]
+    fn jsonl_lines_start_with_fields() {
+        let diff =
+            compare_jsons(r#"{"a":1}"#, r#"{"a":2}"#, &DiffOptions::default(), None).unwrap();
+        let fields = json!({"left_file": "x.json", "right_file": "y.json"});
+        let mut output = std::io::BufWriter::new(Vec::new());
+        jsonl_with(&diff, fields.as_object().unwrap(), &mut output).unwrap();
+        assert!(output.buffer().is_empty());
+        assert_eq!(
+            String::from_utf8(output.into_inner().unwrap()).unwrap(),
+            r#"{"left_file":"x.json","right_file":"y.json","path":"/a","change":"changed","left":1,"right":2}"#
+                .to_string()
+                + "\n"
+        );
+    }
+
+    #[test]
+    fn moved_elements() {
+        let options = DiffOptions::default().detect_moves(true);
+        let diff = match_json_with_options(&json!({"l": [1, 2]}), &json!({"l": [2, 1]}), &options);
+        assert_eq!(
+            unified(&diff, None),
+            vec!["- l->[l: 0]: 1", "+ l->[l: 1]: 1"]
+        );
+        assert_eq!(stat(&diff, &TextOptions::default()), vec!["l: >1"]);
+        assert!(markdown(&diff).contains("| l->[l: 0] to [l: 1] | 1 | 1 | moved |"));
+        let mut output = Vec::new();
+        jsonl(&diff, &mut output).unwrap();
+        let line: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(
+            line,
+            json!({"path": "/l/0", "change": "moved", "to": "/l/1", "left": 1, "right": 1})
+        );
+    }
+
+    #[test]
+    fn unified_array_elements() {
+        let diff = compare_jsons(
+            r#"{"l":["q"]}"#,
+            r#"{"l":[1,"q"]}"#,
+            &DiffOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(unified(&diff, None), vec!["+ l->[l: 0]: 1"]);
+        let diff = compare_jsons(r#"["q"]"#, "[]", &DiffOptions::default(), None).unwrap();
+        assert_eq!(unified(&diff, None), vec![r#"- [l: 0]: "q""#]);
+    }
+
+    #[test]
+    fn unified_colors() {
+        colored::control::set_override(true);
+        let diff =
+            compare_jsons(r#"{"a":1}"#, r#"{"a":2}"#, &DiffOptions::default(), None).unwrap();
+        let theme = Theme::default();
+        assert_eq!(
+            unified(&diff, Some(&theme)),
+            vec!["- a: 1".red().to_string(), "+ a: 2".green().to_string()]
+        );
+    }
+
+    #[test]
+    fn unified_root_value() {
+        let diff = compare_jsons("1", "2", &DiffOptions::default(), None).unwrap();
+        assert_eq!(unified(&diff, None), vec!["- 1", "+ 2"]);
+    }
+}
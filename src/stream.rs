@@ -0,0 +1,265 @@
+use std::io::BufRead;
+
+use indexmap::IndexMap;
+use serde::de::Error as _;
+use serde_json::Value;
+
+use crate::ds::key_node::KeyNode;
+use crate::ds::mismatch::Mismatch;
+use crate::enums::{Error, Which};
+use crate::options::DiffOptions;
+use crate::process::match_json_with_options;
+
+/// Reads the elements of a top-level json array one at a time, so that only a single element
+/// is held in memory. Errors name the document `which` the array is read from.
+pub struct ArrayElements<R: BufRead> {
+    reader: R,
+    which: Which,
+    state: State,
+}
+
+#[derive(PartialEq)]
+enum State {
+    BeforeArray,
+    InArray,
+    Done,
+}
+
+impl<R: BufRead> ArrayElements<R> {
+    pub fn new(reader: R, which: Which) -> Self {
+        Self {
+            reader,
+            which,
+            state: State::BeforeArray,
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, Error> {
+        match self.reader.fill_buf() {
+            Ok(buf) => Ok(buf.first().copied()),
+            Err(err) => Err(Error::Decode(self.which.to_string(), err)),
+        }
+    }
+
+    fn syntax_error(&self, err: serde_json::Error) -> Error {
+        Error::Json {
+            which: self.which,
+            err,
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, Error> {
+        let byte = self.peek()?;
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, Error> {
+        while let Some(byte) = self.peek()? {
+            if !byte.is_ascii_whitespace() {
+                return Ok(Some(byte));
+            }
+            self.reader.consume(1);
+        }
+        Ok(None)
+    }
+
+    /// Collects the bytes of the next element up to the `,` or `]` that ends it
+    fn read_element(&mut self) -> Result<Option<Value>, Error> {
+        if self.state == State::BeforeArray {
+            if self.skip_whitespace()? != Some(b'[') {
+                let err = serde_json::Error::custom("expected a top-level array");
+                return Err(self.syntax_error(err));
+            }
+            self.reader.consume(1);
+            self.state = State::InArray;
+            if self.skip_whitespace()? == Some(b']') {
+                self.state = State::Done;
+            }
+        }
+        if self.state == State::Done {
+            return Ok(None);
+        }
+
+        let mut bytes = Vec::new();
+        let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+        loop {
+            let byte = self.next_byte()?.ok_or_else(|| {
+                self.syntax_error(serde_json::Error::custom(
+                    "EOF while parsing the top-level array",
+                ))
+            })?;
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+            } else {
+                match byte {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' if depth > 0 => depth -= 1,
+                    b',' if depth == 0 => break,
+                    b']' => {
+                        self.state = State::Done;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            bytes.push(byte);
+        }
+        match serde_json::from_slice(&bytes) {
+            Ok(element) => Ok(Some(element)),
+            Err(err) => Err(self.syntax_error(err)),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ArrayElements<R> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let element = self.read_element();
+        if element.is_err() {
+            self.state = State::Done;
+        }
+        element.transpose()
+    }
+}
+
+/// Compares two top-level arrays element by element while reading them, without holding
+/// either array in memory. Elements are compared by position only: `on_difference` gets one
+/// mismatch per index that differs, with paths starting at the `[l: i]` label of the element,
+/// and one per element that only exists in the longer array. Equal elements are skipped.
+/// Syntax errors name the source they were found in.
+pub fn match_array_streams<L: BufRead, R: BufRead>(
+    left: L,
+    right: R,
+    options: &DiffOptions,
+    mut on_difference: impl FnMut(Mismatch) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut left = ArrayElements::new(left, Which::Left);
+    let mut right = ArrayElements::new(right, Which::Right);
+    for index in 0.. {
+        let label = format!("[l: {index}]");
+        let a = left.next().transpose()?;
+        let b = right.next().transpose()?;
+        let mismatch = match (a, b) {
+            (None, None) => break,
+            (Some(a), Some(b)) => {
+                let Mismatch {
+                    left_only_keys,
+                    right_only_keys,
+                    keys_in_both,
+                    moved,
+                    equal_leaves,
+                } = match_json_with_options(&a, &b, options);
+                Mismatch::new(
+                    at_label(&label, left_only_keys),
+                    at_label(&label, right_only_keys),
+                    at_label(&label, keys_in_both),
+                )
+                .with_moves(
+                    moved
+                        .into_iter()
+                        .map(|mut m| {
+                            m.path.insert(0, label.clone());
+                            m
+                        })
+                        .collect(),
+                )
+                .with_equal_leaves(equal_leaves)
+            }
+            (Some(a), None) => Mismatch::new(one_sided(&label, &a), KeyNode::Nil, KeyNode::Nil),
+            (None, Some(b)) => Mismatch::new(KeyNode::Nil, one_sided(&label, &b), KeyNode::Nil),
+        };
+        if !mismatch.is_empty() {
+            on_difference(mismatch)?;
+        }
+    }
+    Ok(())
+}
+
+fn at_label(label: &str, node: KeyNode) -> KeyNode {
+    match node {
+        KeyNode::Nil => KeyNode::Nil,
+        node => KeyNode::Node(IndexMap::from([(label.to_string(), node)])),
+    }
+}
+
+fn one_sided(label: &str, value: &Value) -> KeyNode {
+    KeyNode::Node(IndexMap::from([(format!("{label}-{value}"), KeyNode::Nil)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{text, TextOptions};
+    use serde_json::json;
+
+    fn stream_lines(a: &str, b: &str) -> Result<Vec<String>, Error> {
+        let mut lines = Vec::new();
+        match_array_streams(
+            a.as_bytes(),
+            b.as_bytes(),
+            &DiffOptions::default(),
+            |mismatch| {
+                lines.extend(text(&mismatch, &TextOptions::default()));
+                Ok(())
+            },
+        )?;
+        Ok(lines)
+    }
+
+    #[test]
+    fn elements_are_read_one_by_one() {
+        let input = r#" [1, "a,]", {"b": [2, {"c": "\"]"}]}, [], null ] "#;
+        let elements: Vec<Value> = ArrayElements::new(input.as_bytes(), Which::Left)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            elements,
+            vec![
+                json!(1),
+                json!("a,]"),
+                json!({"b": [2, {"c": "\"]"}]}),
+                json!([]),
+                json!(null)
+            ]
+        );
+        assert_eq!(
+            ArrayElements::new(" [ ] ".as_bytes(), Which::Left).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn positional_differences() {
+        let lines = stream_lines(r#"[1, {"a": 1}, 3, 4]"#, r#"[1, {"a": 2}, 3]"#).unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                "Value changed: [l: 1]->a->{1!=2}",
+                "Extra on left: [l: 3]-4"
+            ]
+        );
+        let lines = stream_lines("[]", "[[1]]").unwrap();
+        assert_eq!(lines, vec!["Extra on right: [l: 0]-[1]"]);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let which = |result: Result<Vec<String>, Error>| match result {
+            Err(Error::Json { which, .. }) => Some(which),
+            _ => None,
+        };
+        assert_eq!(which(stream_lines("{}", "[]")), Some(Which::Left));
+        assert_eq!(which(stream_lines("[1, 2]", "[1, 2")), Some(Which::Right));
+        assert_eq!(which(stream_lines("[1, tru]", "[1]")), Some(Which::Left));
+    }
+}